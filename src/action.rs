@@ -6,9 +6,14 @@ pub enum ActionKind {
     CreatingCircle,
     CreatingRectangle,
     CreatingTriangle,
+    CreatingPolygon,
     CreatingFixedJoint,
     CreatingSlidingJoint,
     CreatingRotatingJoint,
+    CreatingThruster,
+    CreatingCannon,
+    CreatingText,
+    PlacingParts,
 }
 
 pub struct Action {
@@ -21,6 +26,8 @@ pub struct Action {
     step: usize,
     first_body: Option<usize>,
     second_body: Option<usize>,
+    // the corners placed so far by the polygon tool, in world space
+    points: Vec<Point2<f64>>,
 }
 
 impl Action {
@@ -31,6 +38,9 @@ impl Action {
         self.first_click_world = Point2::origin();
         self.second_click = nalgebra::zero();
         self.second_click_world = Point2::origin();
+        self.first_body = None;
+        self.second_body = None;
+        self.points.clear();
     }
 
     pub fn kind(&self) -> ActionKind {
@@ -85,6 +95,24 @@ impl Action {
         self.second_click_world = second_click_world;
         self
     }
+
+    pub fn first_body(&self) -> Option<usize> {
+        self.first_body
+    }
+
+    pub fn set_first_body(&mut self, first_body: Option<usize>) -> &mut Self {
+        self.first_body = first_body;
+        self
+    }
+
+    pub fn points(&self) -> &[Point2<f64>] {
+        &self.points
+    }
+
+    pub fn add_point(&mut self, point: Point2<f64>) -> &mut Self {
+        self.points.push(point);
+        self
+    }
 }
 
 impl Default for Action {
@@ -98,6 +126,7 @@ impl Default for Action {
             step: 0,
             first_body: None,
             second_body: None,
+            points: Vec::new(),
         }
     }
 }
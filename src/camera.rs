@@ -1,4 +1,17 @@
-use nalgebra::Vector2;
+use crate::util;
+use nalgebra::{Point2, Vector2};
+use std::f64::EPSILON;
+
+// in pixels per meter
+pub const MIN_ZOOM: f64 = 2.0;
+pub const MAX_ZOOM: f64 = 300.0;
+
+// how much of the view the followed shapes can move around in before the camera moves
+const DEAD_ZONE: f64 = 0.3;
+// how quickly the camera catches up with what it follows, higher is faster
+const FOLLOW_SPEED: f64 = 4.0;
+// how much room is left around the followed shapes when zooming to fit them
+const FIT_PADDING: f64 = 1.5;
 
 // camera holds information about the current viewport, and can translate from screen space to world space
 #[derive(Clone, Copy, Debug)]
@@ -26,7 +39,14 @@ impl Camera {
     }
 
     pub fn set_zoom(&mut self, zoom: f64) {
-        self.zoom = zoom;
+        self.zoom = util::clamp(zoom, MIN_ZOOM, MAX_ZOOM);
+    }
+
+    // zooms by a factor, keeping whatever is under `anchor` (in screen space) where it is
+    pub fn zoom_about(&mut self, anchor: Vector2<f64>, factor: f64) {
+        let before = self.to_local(anchor);
+        self.set_zoom(self.zoom * factor);
+        self.position += before - self.to_local(anchor);
     }
 
     pub fn size(&self) -> &Vector2<f64> {
@@ -44,6 +64,43 @@ impl Camera {
         self.position += amount;
     }
 
+    // puts a point in the world in the middle of the view
+    pub fn center_on(&mut self, point: Point2<f64>) {
+        self.position = point.coords;
+    }
+
+    // drags the view by an amount in screen space, so the world moves along with the mouse
+    pub fn drag(&mut self, amount: Vector2<f64>) {
+        self.position -= amount / self.zoom;
+    }
+
+    // moves smoothly towards the box (min, max) once it leaves the dead zone, and zooms out to fit
+    // it if asked, but never zooms in past `max_zoom`
+    pub fn follow(
+        &mut self,
+        dt: f64,
+        min: Point2<f64>,
+        max: Point2<f64>,
+        zoom_to_fit: bool,
+        max_zoom: f64,
+    ) {
+        let catch_up = (FOLLOW_SPEED * dt).min(1.0);
+        if zoom_to_fit {
+            let extent = (max - min) * FIT_PADDING;
+            let fit =
+                (self.size.x / extent.x.max(EPSILON)).min(self.size.y / extent.y.max(EPSILON));
+            let zoom = self.zoom + (fit.min(max_zoom) - self.zoom) * catch_up;
+            self.set_zoom(zoom);
+        }
+        let offset = nalgebra::center(&min, &max).coords - self.position;
+        let slack = self.size * DEAD_ZONE / (2.0 * self.zoom);
+        let outside = Vector2::new(
+            offset.x - util::clamp(offset.x, -slack.x, slack.x),
+            offset.y - util::clamp(offset.y, -slack.y, slack.y),
+        );
+        self.position += outside * catch_up;
+    }
+
     // converts from global (screen) to world (local) space
     pub fn to_local(&self, global: Vector2<f64>) -> Vector2<f64> {
         self.position + (global - self.size / 2.0) / self.zoom
@@ -54,3 +111,37 @@ impl Camera {
         self.zoom * (local - self.position) + self.size / 2.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vector2<f64>, b: Vector2<f64>) {
+        assert!((a - b).norm() < 1e-9, "{} is not {}", a, b);
+    }
+
+    #[test]
+    fn zoom_about_keeps_the_anchor_still() {
+        let mut camera = Camera::new(800.0, 600.0);
+        let anchor = Vector2::new(200.0, 150.0);
+        let before = camera.to_local(anchor);
+        camera.zoom_about(anchor, 2.0);
+        assert_eq!(camera.zoom(), 60.0);
+        assert_near(camera.to_local(anchor), before);
+        camera.zoom_about(anchor, 0.25);
+        assert_eq!(camera.zoom(), 15.0);
+        assert_near(camera.to_local(anchor), before);
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let mut camera = Camera::new(800.0, 600.0);
+        let anchor = Vector2::new(400.0, 300.0);
+        let before = camera.to_local(anchor);
+        camera.zoom_about(anchor, 1000.0);
+        assert_eq!(camera.zoom(), MAX_ZOOM);
+        assert_near(camera.to_local(anchor), before);
+        camera.set_zoom(0.0);
+        assert_eq!(camera.zoom(), MIN_ZOOM);
+    }
+}
@@ -0,0 +1,457 @@
+use crate::action::ActionKind;
+use crate::file::FileError;
+use crate::part::{JointKind, Part, Shape, ShapeBuilder, ShapeKind};
+use nalgebra::{Point2, Vector2};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+// bump this whenever the layout of a challenge changes
+const FORMAT_VERSION: u32 = 1;
+
+// an axis aligned box in world space
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Region {
+    pub min: (f64, f64),
+    pub max: (f64, f64),
+}
+
+impl Region {
+    pub fn min(&self) -> Point2<f64> {
+        Point2::new(self.min.0, self.min.1)
+    }
+
+    pub fn max(&self) -> Point2<f64> {
+        Point2::new(self.max.0, self.max.1)
+    }
+
+    pub fn contains(&self, point: Point2<f64>) -> bool {
+        point.x >= self.min.0
+            && point.x <= self.max.0
+            && point.y >= self.min.1
+            && point.y <= self.max.1
+    }
+
+    pub fn contains_box(&self, min: Point2<f64>, max: Point2<f64>) -> bool {
+        self.contains(min) && self.contains(max)
+    }
+}
+
+// a shape that comes with the challenge and can't be edited
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Terrain {
+    kind: ShapeKind,
+    position: (f64, f64),
+    #[serde(default)]
+    rotation: f64,
+    color: [f32; 4],
+    // terrain is static unless told otherwise, so it can be used as an object to push around
+    #[serde(default = "default_ground")]
+    ground: bool,
+}
+
+fn default_ground() -> bool {
+    true
+}
+
+impl Terrain {
+    fn build(&self) -> Shape {
+        let mut builder = match self.kind {
+            ShapeKind::Circle { radius } => ShapeBuilder::circle(radius),
+            ShapeKind::Rectangle {
+                half_width,
+                half_height,
+            } => ShapeBuilder::rectangle(half_width, half_height),
+            ShapeKind::Triangle { p1, p2, p3 } => ShapeBuilder::triangle(p1, p2, p3),
+            ShapeKind::Polygon { ref points } => ShapeBuilder::polygon(points.clone()),
+        };
+        builder
+            .position(Vector2::new(self.position.0, self.position.1))
+            .rotation(self.rotation)
+            .color(self.color)
+            .ground(self.ground)
+            .build()
+    }
+}
+
+// the kinds of parts a challenge can allow
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum PartKind {
+    Circle,
+    Rectangle,
+    Triangle,
+    Polygon,
+    FixedJoint,
+    SlidingJoint,
+    RotatingJoint,
+    Thruster,
+    Cannon,
+}
+
+impl PartKind {
+    fn from_action(kind: ActionKind) -> Option<Self> {
+        match kind {
+            ActionKind::CreatingCircle => Some(PartKind::Circle),
+            ActionKind::CreatingRectangle => Some(PartKind::Rectangle),
+            ActionKind::CreatingTriangle => Some(PartKind::Triangle),
+            ActionKind::CreatingPolygon => Some(PartKind::Polygon),
+            ActionKind::CreatingFixedJoint => Some(PartKind::FixedJoint),
+            ActionKind::CreatingSlidingJoint => Some(PartKind::SlidingJoint),
+            ActionKind::CreatingRotatingJoint => Some(PartKind::RotatingJoint),
+            ActionKind::CreatingThruster => Some(PartKind::Thruster),
+            ActionKind::CreatingCannon => Some(PartKind::Cannon),
+            _ => None,
+        }
+    }
+
+    // what a part that already exists counts as, text is always allowed
+    fn from_part(part: &dyn Part) -> Option<Self> {
+        if let Some(shape) = part.as_shape() {
+            return Some(match shape.kind() {
+                ShapeKind::Circle { .. } => PartKind::Circle,
+                ShapeKind::Rectangle { .. } => PartKind::Rectangle,
+                ShapeKind::Triangle { .. } => PartKind::Triangle,
+                ShapeKind::Polygon { .. } => PartKind::Polygon,
+            });
+        }
+        if let Some(joint) = part.as_joint() {
+            return Some(match joint.kind() {
+                JointKind::Fixed => PartKind::FixedJoint,
+                JointKind::Prismatic => PartKind::SlidingJoint,
+                JointKind::Revolute => PartKind::RotatingJoint,
+            });
+        }
+        if part.as_thruster().is_some() {
+            return Some(PartKind::Thruster);
+        }
+        if part.as_cannon().is_some() {
+            return Some(PartKind::Cannon);
+        }
+        None
+    }
+}
+
+// heights go up, even though y in the world goes down
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum Goal {
+    // any part of the robot reaches the region
+    Reach {
+        region: Region,
+    },
+    // a terrain object stays above a height for some amount of seconds
+    StayAbove {
+        object: usize,
+        height: f64,
+        seconds: f64,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Challenge {
+    version: u32,
+    name: String,
+    terrain: Vec<Terrain>,
+    // new parts have to fit in here
+    build_area: Region,
+    // when empty everything is allowed
+    #[serde(default)]
+    allowed: Vec<PartKind>,
+    goals: Vec<Goal>,
+    // the challenge is failed if the goals aren't met in time
+    #[serde(default)]
+    time_limit: Option<f64>,
+}
+
+impl Challenge {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let reader = BufReader::new(File::open(path)?);
+        let challenge: Challenge = ron::de::from_reader(reader)?;
+        if challenge.version != FORMAT_VERSION {
+            return Err(FileError::UnsupportedVersion {
+                found: challenge.version,
+                expected: FORMAT_VERSION,
+            });
+        }
+        for goal in &challenge.goals {
+            if let Goal::StayAbove { object, .. } = goal {
+                if *object >= challenge.terrain.len() {
+                    return Err(FileError::BadReference(*object));
+                }
+            }
+        }
+        Ok(challenge)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn build_area(&self) -> Region {
+        self.build_area
+    }
+
+    pub fn goals(&self) -> &[Goal] {
+        &self.goals
+    }
+
+    // the terrain always comes first in the part list
+    pub fn terrain_parts(&self) -> Vec<Box<dyn Part>> {
+        self.terrain
+            .iter()
+            .map(|terrain| Box::new(terrain.build()) as Box<dyn Part>)
+            .collect()
+    }
+
+    pub fn terrain_count(&self) -> usize {
+        self.terrain.len()
+    }
+
+    pub fn allows(&self, kind: ActionKind) -> bool {
+        self.allows_kind(PartKind::from_action(kind))
+    }
+
+    // parts that are pasted or inserted have to follow the same rules as new ones
+    pub fn allows_part(&self, part: &dyn Part) -> bool {
+        self.allows_kind(PartKind::from_part(part))
+    }
+
+    fn allows_kind(&self, kind: Option<PartKind>) -> bool {
+        match kind {
+            Some(kind) => self.allowed.is_empty() || self.allowed.contains(&kind),
+            None => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    InProgress,
+    Success,
+    Failure,
+}
+
+// a loaded challenge and how well the current run is going
+pub struct ChallengeState {
+    challenge: Challenge,
+    elapsed: f64,
+    // how long each goal has been met for, in seconds
+    met_for: Vec<f64>,
+    outcome: Outcome,
+}
+
+impl ChallengeState {
+    pub fn new(challenge: Challenge) -> Self {
+        let goals = challenge.goals.len();
+        ChallengeState {
+            challenge,
+            elapsed: 0.0,
+            met_for: vec![0.0; goals],
+            outcome: Outcome::InProgress,
+        }
+    }
+
+    pub fn challenge(&self) -> &Challenge {
+        &self.challenge
+    }
+
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    pub fn outcome(&self) -> Outcome {
+        self.outcome
+    }
+
+    pub fn is_terrain(&self, index: usize) -> bool {
+        index < self.challenge.terrain_count()
+    }
+
+    // called when a run starts
+    pub fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.met_for.iter_mut().for_each(|t| *t = 0.0);
+        self.outcome = Outcome::InProgress;
+    }
+
+    // checks the goals against the running parts, called once per step of the world with its
+    // timestep so the timing doesn't depend on the frame rate, once the outcome is known it won't
+    // change
+    pub fn update(&mut self, timestep: f64, parts: &[Box<dyn Part>]) {
+        if self.outcome != Outcome::InProgress {
+            return;
+        }
+        self.elapsed += timestep;
+        let terrain_count = self.challenge.terrain_count();
+        let position_of = |index: usize| {
+            parts[index]
+                .as_shape()
+                .map(|shape| Point2::from(shape.world_iso().translation.vector))
+        };
+        for (goal, met_for) in self.challenge.goals.iter().zip(&mut self.met_for) {
+            let met = match goal {
+                Goal::Reach { region } => (terrain_count..parts.len())
+                    .filter_map(&position_of)
+                    .any(|position| region.contains(position)),
+                Goal::StayAbove { object, height, .. } => match position_of(*object) {
+                    Some(position) => -position.y > *height,
+                    None => false,
+                },
+            };
+            *met_for = if met { *met_for + timestep } else { 0.0 };
+        }
+        let all_met = self
+            .challenge
+            .goals
+            .iter()
+            .zip(&self.met_for)
+            .all(|(goal, met_for)| match goal {
+                Goal::Reach { .. } => *met_for > 0.0,
+                Goal::StayAbove { seconds, .. } => *met_for >= *seconds,
+            });
+        if all_met {
+            self.outcome = Outcome::Success;
+        } else if let Some(time_limit) = self.challenge.time_limit {
+            if self.elapsed > time_limit {
+                self.outcome = Outcome::Failure;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part::{JointBuilder, Text, Thruster};
+    use nalgebra::{Isometry2, Vector2};
+
+    const TIMESTEP: f64 = 0.25;
+
+    fn region(min: (f64, f64), max: (f64, f64)) -> Region {
+        Region { min, max }
+    }
+
+    fn challenge(allowed: Vec<PartKind>, goals: Vec<Goal>, time_limit: Option<f64>) -> Challenge {
+        Challenge {
+            version: FORMAT_VERSION,
+            name: "Test".to_owned(),
+            terrain: vec![Terrain {
+                kind: ShapeKind::Circle { radius: 1.0 },
+                position: (0.0, 0.0),
+                rotation: 0.0,
+                color: [1.0; 4],
+                ground: false,
+            }],
+            build_area: region((-10.0, -10.0), (10.0, 10.0)),
+            allowed,
+            goals,
+            time_limit,
+        }
+    }
+
+    // the terrain followed by a single robot circle
+    fn parts(challenge: &Challenge) -> Vec<Box<dyn Part>> {
+        let mut parts = challenge.terrain_parts();
+        parts.push(Box::new(ShapeBuilder::circle(1.0).build()));
+        parts
+    }
+
+    fn move_to(part: &mut Box<dyn Part>, x: f64, y: f64) {
+        part.as_shape_mut()
+            .unwrap()
+            .set_world_iso(Isometry2::new(Vector2::new(x, y), 0.0));
+    }
+
+    #[test]
+    fn everything_allowed_when_empty() {
+        let challenge = challenge(vec![], vec![], None);
+        assert!(challenge.allows(ActionKind::CreatingCannon));
+        assert!(challenge.allows_part(&JointBuilder::fixed(0, 1).build()));
+    }
+
+    #[test]
+    fn only_allowed_kinds() {
+        let challenge = challenge(vec![PartKind::Circle, PartKind::Thruster], vec![], None);
+        assert!(challenge.allows(ActionKind::CreatingCircle));
+        assert!(challenge.allows(ActionKind::CreatingThruster));
+        assert!(!challenge.allows(ActionKind::CreatingRectangle));
+        assert!(!challenge.allows(ActionKind::CreatingRotatingJoint));
+        // things that aren't parts, and text, are never blocked
+        assert!(challenge.allows(ActionKind::None));
+        assert!(challenge.allows(ActionKind::CreatingText));
+        assert!(challenge.allows(ActionKind::PlacingParts));
+        assert!(challenge.allows_part(&ShapeBuilder::circle(1.0).build()));
+        assert!(challenge.allows_part(&Thruster::new(0, Point2::origin(), 0.0)));
+        assert!(challenge.allows_part(&Text::new("Hi".to_owned(), Point2::origin(), None)));
+        assert!(!challenge.allows_part(&ShapeBuilder::rectangle(1.0, 1.0).build()));
+        assert!(!challenge.allows_part(&JointBuilder::prismatic(0, 1).build()));
+    }
+
+    #[test]
+    fn reach_is_met_by_the_robot() {
+        let goal = Goal::Reach {
+            region: region((4.0, -1.0), (6.0, 1.0)),
+        };
+        let challenge = challenge(vec![], vec![goal], None);
+        let mut parts = parts(&challenge);
+        let mut state = ChallengeState::new(challenge);
+        // terrain in the region doesn't count
+        move_to(&mut parts[0], 5.0, 0.0);
+        state.update(TIMESTEP, &parts);
+        assert_eq!(state.outcome(), Outcome::InProgress);
+        move_to(&mut parts[1], 5.0, 0.0);
+        state.update(TIMESTEP, &parts);
+        assert_eq!(state.outcome(), Outcome::Success);
+        assert_eq!(state.elapsed(), 2.0 * TIMESTEP);
+    }
+
+    #[test]
+    fn stay_above_needs_to_last() {
+        let goal = Goal::StayAbove {
+            object: 0,
+            height: 2.0,
+            seconds: 1.0,
+        };
+        let challenge = challenge(vec![], vec![goal], None);
+        let mut parts = parts(&challenge);
+        let mut state = ChallengeState::new(challenge);
+        // up is negative y
+        move_to(&mut parts[0], 0.0, -3.0);
+        for _ in 0..3 {
+            state.update(TIMESTEP, &parts);
+        }
+        // dropping below starts the count again
+        move_to(&mut parts[0], 0.0, 3.0);
+        state.update(TIMESTEP, &parts);
+        move_to(&mut parts[0], 0.0, -3.0);
+        for _ in 0..3 {
+            state.update(TIMESTEP, &parts);
+        }
+        assert_eq!(state.outcome(), Outcome::InProgress);
+        state.update(TIMESTEP, &parts);
+        assert_eq!(state.outcome(), Outcome::Success);
+    }
+
+    #[test]
+    fn fails_after_the_time_limit() {
+        let goal = Goal::Reach {
+            region: region((4.0, -1.0), (6.0, 1.0)),
+        };
+        let challenge = challenge(vec![], vec![goal], Some(1.0));
+        let mut parts = parts(&challenge);
+        let mut state = ChallengeState::new(challenge);
+        for _ in 0..4 {
+            state.update(TIMESTEP, &parts);
+        }
+        assert_eq!(state.outcome(), Outcome::InProgress);
+        state.update(TIMESTEP, &parts);
+        assert_eq!(state.outcome(), Outcome::Failure);
+        // the outcome sticks once it's known
+        move_to(&mut parts[1], 5.0, 0.0);
+        state.update(TIMESTEP, &parts);
+        assert_eq!(state.outcome(), Outcome::Failure);
+        state.reset();
+        state.update(TIMESTEP, &parts);
+        assert_eq!(state.outcome(), Outcome::Success);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::part::{self, Part, PartData};
+
+// holds copied parts, joints only come along when both of their shapes do
+#[derive(Default)]
+pub struct Clipboard {
+    parts: Vec<PartData>,
+}
+
+impl Clipboard {
+    // selected parts that aren't attached to anything, like free text, come along too
+    pub fn copy(&mut self, parts: &[Box<dyn Part>], indices: &[usize]) {
+        self.parts = part::extract(parts, indices)
+            .iter()
+            .map(|part| part.to_data())
+            .collect();
+        log::info!("Copied {} parts", self.parts.len());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    // a fresh copy of everything on the clipboard, ready to be placed
+    pub fn parts(&self) -> Vec<Box<dyn Part>> {
+        self.parts
+            .iter()
+            .cloned()
+            .map(PartData::into_part)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part::{JointBuilder, ShapeBuilder, Text, Thruster};
+    use nalgebra::Point2;
+
+    fn robot() -> Vec<Box<dyn Part>> {
+        vec![
+            Box::new(ShapeBuilder::circle(1.0).build()),
+            Box::new(ShapeBuilder::circle(1.0).build()),
+            Box::new(ShapeBuilder::circle(1.0).build()),
+            Box::new(JointBuilder::revolute(0, 2).build()),
+            Box::new(Thruster::new(2, Point2::origin(), 0.0)),
+            Box::new(Text::new("Free".to_owned(), Point2::origin(), None)),
+            Box::new(Text::new("Label".to_owned(), Point2::origin(), Some(1))),
+        ]
+    }
+
+    #[test]
+    fn copies_attached_parts_and_remaps_them() {
+        let mut clipboard = Clipboard::default();
+        clipboard.copy(&robot(), &[2, 0]);
+        let parts = clipboard.parts();
+        let names = parts.iter().map(|part| part.name()).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["Circle", "Circle", "Rotating joint", "Thruster"]
+        );
+        assert_eq!(parts[2].shapes(), vec![0, 1]);
+        assert_eq!(parts[3].shapes(), vec![1]);
+    }
+
+    #[test]
+    fn leaves_joints_to_uncopied_shapes_behind() {
+        let mut clipboard = Clipboard::default();
+        clipboard.copy(&robot(), &[0, 3]);
+        let parts = clipboard.parts();
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].as_shape().is_some());
+    }
+
+    #[test]
+    fn copies_selected_free_parts() {
+        let mut clipboard = Clipboard::default();
+        clipboard.copy(&robot(), &[5]);
+        let parts = clipboard.parts();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].as_text().map(|text| text.content()), Some("Free"));
+    }
+
+    #[test]
+    fn nothing_copied_is_empty() {
+        let mut clipboard = Clipboard::default();
+        assert!(clipboard.is_empty());
+        clipboard.copy(&robot(), &[]);
+        assert!(clipboard.is_empty());
+    }
+}
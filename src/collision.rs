@@ -0,0 +1,128 @@
+use crate::part::Part;
+use ncollide2d::broad_phase::BroadPhasePairFilter;
+use ncollide2d::world::{CollisionGroups, CollisionObject};
+use nphysics2d::object::{BodyHandle, ColliderData};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// how many layers shapes can be put on, each one is a collision group
+pub const LAYER_COUNT: usize = 8;
+// bodies that aren't shapes, like projectiles, hit everything that collides
+const LOOSE_GROUP: usize = 28;
+// only used to find shapes under the mouse
+const QUERY_GROUP: usize = 29;
+
+// which layers collide with which, every layer starts out colliding with everything
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Layers {
+    names: Vec<String>,
+    matrix: [[bool; LAYER_COUNT]; LAYER_COUNT],
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Layers {
+            names: (1..=LAYER_COUNT)
+                .map(|layer| format!("Layer {}", layer))
+                .collect(),
+            matrix: [[true; LAYER_COUNT]; LAYER_COUNT],
+        }
+    }
+}
+
+impl Layers {
+    pub fn name(&self, layer: usize) -> &str {
+        &self.names[layer]
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn set_name(&mut self, layer: usize, name: String) {
+        self.names[layer] = name;
+    }
+
+    pub fn collides(&self, layer1: usize, layer2: usize) -> bool {
+        self.matrix[layer1][layer2]
+    }
+
+    // the matrix is always kept symmetric
+    pub fn set_collides(&mut self, layer1: usize, layer2: usize, collides: bool) {
+        self.matrix[layer1][layer2] = collides;
+        self.matrix[layer2][layer1] = collides;
+    }
+
+    // works out the groups of every shape, indexed like the part list, everything else gets the
+    // default groups
+    pub fn collision_groups(&self, parts: &[Box<dyn Part>]) -> Vec<CollisionGroups> {
+        parts
+            .iter()
+            .map(|part| match part.as_shape() {
+                Some(shape) => self.layer_groups(shape.layer()),
+                None => CollisionGroups::default(),
+            })
+            .collect()
+    }
+
+    fn layer_groups(&self, layer: usize) -> CollisionGroups {
+        let mut whitelist = (0..LAYER_COUNT)
+            .filter(|&other| self.collides(layer, other))
+            .collect::<Vec<_>>();
+        whitelist.push(LOOSE_GROUP);
+        whitelist.push(QUERY_GROUP);
+        CollisionGroups::new()
+            .with_membership(&[layer])
+            .with_whitelist(&whitelist)
+    }
+}
+
+// groups that only the mouse uses, every shape can be found with these
+pub fn query_groups() -> CollisionGroups {
+    CollisionGroups::new().with_membership(&[QUERY_GROUP])
+}
+
+// groups for bodies that aren't shapes
+pub fn loose_groups() -> CollisionGroups {
+    CollisionGroups::new().with_membership(&[LOOSE_GROUP])
+}
+
+// keeps the two shapes of a joint from colliding, unless the joint says they should, there's no
+// limit on how many joints there can be like there is with groups
+pub struct JointFilter {
+    pairs: HashSet<(BodyHandle, BodyHandle)>,
+}
+
+impl JointFilter {
+    // the bodies are indexed like the part list, and must already be created
+    pub fn new(parts: &[Box<dyn Part>], bodies: &[Option<BodyHandle>]) -> Self {
+        let mut pairs = HashSet::new();
+        for joint in parts.iter().filter_map(|part| part.as_joint()) {
+            if joint.collide_connected() {
+                continue;
+            }
+            if let (Some(body1), Some(body2)) = (bodies[joint.shape1()], bodies[joint.shape2()]) {
+                pairs.insert((body1, body2));
+                pairs.insert((body2, body1));
+            }
+        }
+        JointFilter { pairs }
+    }
+}
+
+impl BroadPhasePairFilter<f64, ColliderData<f64>> for JointFilter {
+    fn is_pair_valid(
+        &self,
+        object1: &CollisionObject<f64, ColliderData<f64>>,
+        object2: &CollisionObject<f64, ColliderData<f64>>,
+    ) -> bool {
+        !self
+            .pairs
+            .contains(&(object1.data().body(), object2.data().body()))
+    }
+}
+
+// a shape that doesn't collide with anything can still be found by the mouse
+pub fn without_collisions(groups: CollisionGroups) -> CollisionGroups {
+    groups.with_whitelist(&[QUERY_GROUP])
+}
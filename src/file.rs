@@ -0,0 +1,92 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+// everything that can go wrong reading or writing one of our files
+#[derive(Debug)]
+pub enum FileError {
+    Io(io::Error),
+    Encoding(bincode::Error),
+    // hand written files, like challenges, are parsed instead of decoded
+    Parsing(ron::de::Error),
+    // the file doesn't start with the header we expected
+    WrongKind,
+    UnsupportedVersion { found: u32, expected: u32 },
+    // a part refers to a part that doesn't exist or isn't a shape
+    BadReference(usize),
+    // a shape is on a collision layer that doesn't exist
+    BadLayer(usize),
+    // a replay frame doesn't match the parts it should move
+    BadFrame(usize),
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FileError::Io(e) => write!(f, "{}", e),
+            FileError::Encoding(e) => write!(f, "{}", e),
+            FileError::Parsing(e) => write!(f, "{}", e),
+            FileError::WrongKind => write!(f, "not the right kind of file"),
+            FileError::UnsupportedVersion { found, expected } => write!(
+                f,
+                "unsupported format version {} (expected {})",
+                found, expected
+            ),
+            FileError::BadReference(index) => {
+                write!(f, "part {} is not attached to shapes", index)
+            }
+            FileError::BadLayer(index) => {
+                write!(f, "part {} is on a layer that doesn't exist", index)
+            }
+            FileError::BadFrame(index) => write!(f, "frame {} doesn't match the parts", index),
+        }
+    }
+}
+
+impl From<io::Error> for FileError {
+    fn from(e: io::Error) -> Self {
+        FileError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for FileError {
+    fn from(e: bincode::Error) -> Self {
+        FileError::Encoding(e)
+    }
+}
+
+impl From<ron::de::Error> for FileError {
+    fn from(e: ron::de::Error) -> Self {
+        FileError::Parsing(e)
+    }
+}
+
+// the header and version are written on their own so they can be checked
+// before we try to make sense of the rest of the file
+pub fn write_header<W: Write>(
+    writer: &mut W,
+    header: &[u8; 8],
+    version: u32,
+) -> Result<(), FileError> {
+    bincode::serialize_into(&mut *writer, header)?;
+    bincode::serialize_into(&mut *writer, &version)?;
+    Ok(())
+}
+
+pub fn read_header<R: Read>(
+    reader: &mut R,
+    header: &[u8; 8],
+    version: u32,
+) -> Result<(), FileError> {
+    let found: [u8; 8] = bincode::deserialize_from(&mut *reader)?;
+    if &found != header {
+        return Err(FileError::WrongKind);
+    }
+    let found: u32 = bincode::deserialize_from(&mut *reader)?;
+    if found != version {
+        return Err(FileError::UnsupportedVersion {
+            found,
+            expected: version,
+        });
+    }
+    Ok(())
+}
@@ -26,6 +26,9 @@ widget_ids! {
         stop_button,
 
         part_count_text,
+        file_name_text_box,
+        status_text,
+        challenge_text,
 
         part_canvas,
         part_name_label,
@@ -36,8 +39,11 @@ widget_ids! {
         part_rotate_button,
         part_density_text,
         part_density_slider,
+        part_friction_slider,
+        part_restitution_slider,
         part_collides_toggle,
         part_collides_text,
+        part_layer_list,
         part_camera_focus_toggle,
         part_camera_focus_text,
         part_undraggable_toggle,
@@ -51,8 +57,60 @@ widget_ids! {
         part_show_outlines_text,
         part_outlines_behind_toggle,
         part_outlines_behind_text,
+        part_joint_collide_toggle,
+        part_joint_collide_text,
+        part_motor_toggle,
+        part_motor_text,
+        part_motor_speed_slider,
+        part_motor_force_slider,
+        part_limits_toggle,
+        part_limits_text,
+        part_lower_limit_slider,
+        part_upper_limit_slider,
+        part_forward_key_button,
+        part_backward_key_button,
+        part_control_mode_button,
+        part_thruster_strength_slider,
+        part_thruster_key_button,
+        part_cannon_speed_slider,
+        part_cannon_size_slider,
+        part_cannon_density_slider,
+        part_cannon_cooldown_slider,
+        part_cannon_key_button,
+        part_text_content_box,
+        part_text_size_slider,
+        part_text_color_button,
+        part_text_attach_toggle,
+        part_text_attach_text,
 
 
+        settings_canvas,
+        settings_title_text,
+        settings_history_depth_dialer,
+        settings_zoom_to_fit_toggle,
+        settings_zoom_to_fit_text,
+        settings_close_button,
+
+        scale_canvas,
+        scale_title_text,
+        scale_factor_dialer,
+        scale_apply_button,
+        scale_close_button,
+
+        layers_canvas,
+        layers_title_text,
+        layers_name_boxes[],
+        layers_column_texts[],
+        layers_matrix_toggles[],
+        layers_close_button,
+
+        replay_canvas,
+        replay_pause_button,
+        replay_seek_slider,
+        replay_speed_slider,
+        replay_keys_text,
+        replay_close_button,
+
         canvas,
         text,
 
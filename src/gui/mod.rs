@@ -1,3 +1,4 @@
+use crate::collision;
 use conrod_core::image::Map;
 use conrod_core::text::rt::Rect;
 use conrod_core::text::GlyphCache;
@@ -51,7 +52,17 @@ impl Gui {
                 .expect("Unable to create texture")
         };
 
-        let ids = Ids::new(ui.widget_id_generator());
+        let mut ids = Ids::new(ui.widget_id_generator());
+        // one of each per layer, and one toggle per pair of layers
+        let mut generator = ui.widget_id_generator();
+        ids.layers_name_boxes
+            .resize(collision::LAYER_COUNT, &mut generator);
+        ids.layers_column_texts
+            .resize(collision::LAYER_COUNT, &mut generator);
+        ids.layers_matrix_toggles.resize(
+            collision::LAYER_COUNT * collision::LAYER_COUNT,
+            &mut generator,
+        );
 
         Gui {
             ui,
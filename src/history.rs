@@ -0,0 +1,241 @@
+use crate::collision::Layers;
+use crate::part::{Part, PartData};
+use std::collections::VecDeque;
+
+pub const DEFAULT_DEPTH: usize = 50;
+pub const MAX_DEPTH: usize = 500;
+
+// the parts and layers as they were right before an edit
+struct Edit {
+    name: &'static str,
+    parts: Vec<PartData>,
+    layers: Layers,
+}
+
+impl Edit {
+    fn new(name: &'static str, parts: &[Box<dyn Part>], layers: &Layers) -> Self {
+        Edit {
+            name,
+            parts: parts.iter().map(|part| part.to_data()).collect(),
+            layers: layers.clone(),
+        }
+    }
+
+    fn restore(self) -> (Vec<Box<dyn Part>>, Layers) {
+        let parts = self.parts.into_iter().map(PartData::into_part).collect();
+        (parts, self.layers)
+    }
+}
+
+// every edit goes through here so it can be undone and redone
+pub struct History {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    // how many edits we remember
+    depth: usize,
+    // the edit that `record_merged` is adding to, and what it's editing
+    merging: Option<(&'static str, Vec<usize>)>,
+}
+
+impl History {
+    pub fn new(depth: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+            merging: None,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+
+    // remembers the parts and layers before an edit is made to them
+    pub fn record(&mut self, name: &'static str, parts: &[Box<dyn Part>], layers: &Layers) {
+        log::trace!("Recording edit: {}", name);
+        self.merging = None;
+        self.undo.push_back(Edit::new(name, parts, layers));
+        if self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+        // a new edit makes anything we undid unreachable
+        self.redo.clear();
+    }
+
+    // like `record`, but repeated edits of the same things during one interaction (dragging a
+    // slider) are undone at once, `end_merge` ends the interaction
+    pub fn record_merged(
+        &mut self,
+        name: &'static str,
+        targets: &[usize],
+        parts: &[Box<dyn Part>],
+        layers: &Layers,
+    ) {
+        let merge = match &self.merging {
+            Some((merging_name, merging_targets)) => {
+                *merging_name == name && merging_targets.as_slice() == targets
+            }
+            None => false,
+        };
+        if !merge {
+            self.record(name, parts, layers);
+            self.merging = Some((name, targets.to_vec()));
+        }
+    }
+
+    pub fn end_merge(&mut self) {
+        self.merging = None;
+    }
+
+    // returns the parts and layers from before the last edit, if there is one
+    pub fn undo(
+        &mut self,
+        parts: &[Box<dyn Part>],
+        layers: &Layers,
+    ) -> Option<(Vec<Box<dyn Part>>, Layers)> {
+        let edit = self.undo.pop_back()?;
+        log::info!("Undoing: {}", edit.name);
+        self.merging = None;
+        self.redo.push(Edit::new(edit.name, parts, layers));
+        Some(edit.restore())
+    }
+
+    // returns the parts and layers from after the last undone edit, if there is one
+    pub fn redo(
+        &mut self,
+        parts: &[Box<dyn Part>],
+        layers: &Layers,
+    ) -> Option<(Vec<Box<dyn Part>>, Layers)> {
+        let edit = self.redo.pop()?;
+        log::info!("Redoing: {}", edit.name);
+        self.merging = None;
+        self.undo.push_back(Edit::new(edit.name, parts, layers));
+        Some(edit.restore())
+    }
+
+    // throws away the last edit, returning the parts and layers from before it
+    pub fn revert(&mut self) -> Option<(Vec<Box<dyn Part>>, Layers)> {
+        let edit = self.undo.pop_back()?;
+        log::info!("Reverting: {}", edit.name);
+        self.merging = None;
+        Some(edit.restore())
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.merging = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part::ShapeBuilder;
+
+    // edits are told apart by how many parts there are
+    fn parts(count: usize) -> Vec<Box<dyn Part>> {
+        (0..count)
+            .map(|_| Box::new(ShapeBuilder::circle(1.0).build()) as Box<dyn Part>)
+            .collect()
+    }
+
+    fn undo_count(history: &mut History, current: usize) -> Option<usize> {
+        history
+            .undo(&parts(current), &Layers::default())
+            .map(|(parts, _)| parts.len())
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let layers = Layers::default();
+        let mut history = History::new(DEFAULT_DEPTH);
+        history.record("Add", &parts(1), &layers);
+        let (undone, _) = history.undo(&parts(2), &layers).unwrap();
+        assert_eq!(undone.len(), 1);
+        let (redone, _) = history.redo(&undone, &layers).unwrap();
+        assert_eq!(redone.len(), 2);
+        assert!(history.redo(&redone, &layers).is_none());
+        assert_eq!(undo_count(&mut history, 2), Some(1));
+        assert_eq!(undo_count(&mut history, 1), None);
+    }
+
+    #[test]
+    fn new_edits_clear_redo() {
+        let layers = Layers::default();
+        let mut history = History::new(DEFAULT_DEPTH);
+        history.record("Add", &parts(1), &layers);
+        history.undo(&parts(2), &layers).unwrap();
+        history.record("Delete", &parts(1), &layers);
+        assert!(history.redo(&parts(0), &layers).is_none());
+    }
+
+    #[test]
+    fn oldest_edits_are_forgotten() {
+        let layers = Layers::default();
+        let mut history = History::new(2);
+        for count in 0..3 {
+            history.record("Add", &parts(count), &layers);
+        }
+        assert_eq!(undo_count(&mut history, 3), Some(2));
+        assert_eq!(undo_count(&mut history, 2), Some(1));
+        assert_eq!(undo_count(&mut history, 1), None);
+    }
+
+    #[test]
+    fn layers_are_restored() {
+        let mut layers = Layers::default();
+        let mut history = History::new(DEFAULT_DEPTH);
+        history.record("Change layer collisions", &parts(0), &layers);
+        layers.set_collides(0, 1, false);
+        let (_, undone) = history.undo(&parts(0), &layers).unwrap();
+        assert!(undone.collides(0, 1));
+        let (_, redone) = history.redo(&parts(0), &undone).unwrap();
+        assert!(!redone.collides(0, 1));
+    }
+
+    #[test]
+    fn merges_within_one_interaction() {
+        let layers = Layers::default();
+        let mut history = History::new(DEFAULT_DEPTH);
+        history.record_merged("Change material", &[0], &parts(1), &layers);
+        history.record_merged("Change material", &[0], &parts(2), &layers);
+        assert_eq!(undo_count(&mut history, 3), Some(1));
+        assert_eq!(undo_count(&mut history, 1), None);
+    }
+
+    #[test]
+    fn does_not_merge_other_interactions_or_parts() {
+        let layers = Layers::default();
+        let mut history = History::new(DEFAULT_DEPTH);
+        history.record_merged("Change material", &[0], &parts(1), &layers);
+        history.end_merge();
+        history.record_merged("Change material", &[0], &parts(2), &layers);
+        history.record_merged("Change material", &[1], &parts(3), &layers);
+        history.record_merged("Change text", &[1], &parts(4), &layers);
+        assert_eq!(undo_count(&mut history, 5), Some(4));
+        assert_eq!(undo_count(&mut history, 4), Some(3));
+        assert_eq!(undo_count(&mut history, 3), Some(2));
+        assert_eq!(undo_count(&mut history, 2), Some(1));
+        assert_eq!(undo_count(&mut history, 1), None);
+    }
+
+    #[test]
+    fn does_not_merge_after_undo() {
+        let layers = Layers::default();
+        let mut history = History::new(DEFAULT_DEPTH);
+        history.record_merged("Change material", &[0], &parts(1), &layers);
+        history.undo(&parts(2), &layers).unwrap();
+        history.record_merged("Change material", &[0], &parts(1), &layers);
+        assert_eq!(undo_count(&mut history, 2), Some(1));
+        assert_eq!(undo_count(&mut history, 1), None);
+    }
+}
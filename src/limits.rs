@@ -6,3 +6,40 @@ pub const MAX_CIRCLE_SIZE: f64 = 10.0;
 
 pub const MIN_TRIANGLE_SIZE: f64 = 0.1;
 pub const MAX_TRIANGLE_SIZE: f64 = 10.0;
+
+pub const MIN_POLYGON_SIZE: f64 = 0.1;
+pub const MAX_POLYGON_SIZE: f64 = 10.0;
+// the polygon tool finishes on its own once this many points are placed
+pub const MAX_POLYGON_POINTS: usize = 16;
+
+pub const MIN_DENSITY: f64 = 0.1;
+pub const MAX_DENSITY: f64 = 30.0;
+pub const MAX_FRICTION: f64 = 2.0;
+pub const MAX_RESTITUTION: f64 = 1.0;
+
+// how much the scale tool can scale by at once
+pub const MIN_SCALE_FACTOR: f64 = 0.1;
+pub const MAX_SCALE_FACTOR: f64 = 10.0;
+
+// how tall text can be
+pub const MIN_TEXT_SIZE: f64 = 0.1;
+pub const MAX_TEXT_SIZE: f64 = 5.0;
+
+// in radians per second
+pub const MAX_MOTOR_SPEED: f64 = 30.0;
+pub const MAX_MOTOR_FORCE: f64 = 5000.0;
+
+// in meters per second
+pub const MAX_PISTON_SPEED: f64 = 10.0;
+// how far a sliding joint can travel either way from where it was built
+pub const MAX_TRAVEL: f64 = 10.0;
+
+pub const MAX_THRUSTER_STRENGTH: f64 = 1000.0;
+
+// in meters per second
+pub const MAX_MUZZLE_SPEED: f64 = 50.0;
+pub const MIN_PROJECTILE_SIZE: f64 = 0.05;
+pub const MAX_PROJECTILE_SIZE: f64 = 1.0;
+pub const MAX_PROJECTILE_DENSITY: f64 = 10.0;
+// in seconds
+pub const MAX_COOLDOWN: f64 = 5.0;
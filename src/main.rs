@@ -6,9 +6,16 @@ use piston::window::{AdvancedWindow, WindowSettings};
 
 mod action;
 mod camera;
+mod challenge;
+mod clipboard;
+mod collision;
+mod file;
 mod gui;
+mod history;
 mod limits;
 mod part;
+mod replay;
+mod robot;
 mod screen;
 mod util;
 mod visualizer;
@@ -0,0 +1,335 @@
+use crate::collision;
+use crate::part::{Mirror, Part, PartData};
+use nalgebra::{Isometry2, Point2, UnitComplex, Vector2};
+use ncollide2d::shape::{Ball, ShapeHandle};
+use nphysics2d::algebra::{ForceType, Velocity2};
+use nphysics2d::object::{BodyHandle, ColliderDesc, RigidBodyDesc};
+use nphysics2d::world::World;
+use piston::input::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// how close to a cannon we have to be to pick it
+const PICK_RADIUS: f64 = 0.3;
+// how far in front of the cannon projectiles appear
+const MUZZLE_LENGTH: f64 = 0.5;
+// the oldest projectiles go away once a cannon has fired this many
+const MAX_PROJECTILES: usize = 50;
+
+// a projectile flying around while running, during a replay it has no body
+#[derive(Clone, Copy, Debug)]
+pub struct Projectile {
+    body: Option<BodyHandle>,
+    position: Point2<f64>,
+}
+
+impl Projectile {
+    pub fn position(&self) -> Point2<f64> {
+        self.position
+    }
+}
+
+// fires balls out along its local x axis, kicking back the shape it's attached to
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Cannon {
+    // the shape we sit on, as an index into the part list
+    shape: usize,
+    iso: Isometry2<f64>,
+    muzzle_speed: f64,
+    projectile_radius: f64,
+    projectile_density: f64,
+    // in seconds
+    cooldown: f64,
+    // without a key the cannon never fires
+    key: Option<Key>,
+    // the body of our shape, only known while running
+    #[serde(skip)]
+    body: Option<BodyHandle>,
+    // where we sit on the body, only known while running
+    #[serde(skip)]
+    local_iso: Option<Isometry2<f64>>,
+    #[serde(skip, default = "Isometry2::identity")]
+    world_iso: Isometry2<f64>,
+    // how long until we can fire again
+    #[serde(skip)]
+    reloading: f64,
+    // we fire when the key goes down, not for as long as it's held
+    #[serde(skip)]
+    key_held: bool,
+    #[serde(skip)]
+    projectiles: Vec<Projectile>,
+    z_order: i32,
+    #[serde(skip)]
+    selected: bool,
+}
+
+impl Cannon {
+    pub fn new(shape: usize, position: Point2<f64>, angle: f64) -> Self {
+        let iso = Isometry2::new(position.coords, angle);
+        Cannon {
+            shape,
+            iso,
+            muzzle_speed: 20.0,
+            projectile_radius: 0.2,
+            projectile_density: 1.0,
+            cooldown: 0.5,
+            key: None,
+            body: None,
+            local_iso: None,
+            world_iso: iso,
+            reloading: 0.0,
+            key_held: false,
+            projectiles: Vec::new(),
+            z_order: 0,
+            selected: false,
+        }
+    }
+
+    pub fn iso(&self) -> Isometry2<f64> {
+        self.iso
+    }
+
+    pub fn world_iso(&self) -> Isometry2<f64> {
+        self.world_iso
+    }
+
+    // used to pose cannons without any physics, such as during a replay
+    pub fn set_world_iso(&mut self, world_iso: Isometry2<f64>) {
+        self.world_iso = world_iso;
+    }
+
+    pub fn muzzle_speed(&self) -> f64 {
+        self.muzzle_speed
+    }
+
+    pub fn set_muzzle_speed(&mut self, muzzle_speed: f64) {
+        self.muzzle_speed = muzzle_speed;
+    }
+
+    pub fn projectile_radius(&self) -> f64 {
+        self.projectile_radius
+    }
+
+    pub fn set_projectile_radius(&mut self, projectile_radius: f64) {
+        self.projectile_radius = projectile_radius;
+    }
+
+    pub fn projectile_density(&self) -> f64 {
+        self.projectile_density
+    }
+
+    pub fn set_projectile_density(&mut self, projectile_density: f64) {
+        self.projectile_density = projectile_density;
+    }
+
+    pub fn cooldown(&self) -> f64 {
+        self.cooldown
+    }
+
+    pub fn set_cooldown(&mut self, cooldown: f64) {
+        self.cooldown = cooldown;
+    }
+
+    pub fn key(&self) -> Option<Key> {
+        self.key
+    }
+
+    pub fn set_key(&mut self, key: Option<Key>) {
+        self.key = key;
+    }
+
+    pub fn projectiles(&self) -> &[Projectile] {
+        &self.projectiles
+    }
+
+    // used to pose projectiles without any physics, such as during a replay
+    pub fn set_projectile_positions(&mut self, positions: &[Point2<f64>]) {
+        self.projectiles = positions
+            .iter()
+            .map(|&position| Projectile {
+                body: None,
+                position,
+            })
+            .collect();
+    }
+
+    // tells the cannon which body to sit on, this must be called before `create`
+    pub fn bind(&mut self, body: BodyHandle) {
+        self.body = Some(body);
+    }
+
+    fn fire(&mut self, world: &mut World<f64>, body: BodyHandle) {
+        let direction = self.world_iso.rotation * Vector2::x();
+        let muzzle = self.world_iso * Point2::new(MUZZLE_LENGTH, 0.0);
+        // projectiles leave with whatever speed the cannon already has
+        let velocity = match world.rigid_body(body) {
+            Some(body) => body.velocity().linear,
+            None => return,
+        };
+        let collider = ColliderDesc::new(ShapeHandle::new(Ball::new(self.projectile_radius)))
+            .density(self.projectile_density)
+            .collision_groups(collision::loose_groups());
+        let projectile = RigidBodyDesc::new()
+            .collider(&collider)
+            .translation(muzzle.coords)
+            .velocity(Velocity2::linear(
+                velocity.x + direction.x * self.muzzle_speed,
+                velocity.y + direction.y * self.muzzle_speed,
+            ))
+            .build(world)
+            .handle();
+        self.projectiles.push(Projectile {
+            body: Some(projectile),
+            position: muzzle,
+        });
+        if self.projectiles.len() > MAX_PROJECTILES {
+            if let Some(oldest) = self.projectiles.remove(0).body {
+                world.remove_bodies(&[oldest]);
+            }
+        }
+        // whatever we throw forwards pushes us backwards
+        let mass = self.projectile_density
+            * std::f64::consts::PI
+            * self.projectile_radius
+            * self.projectile_radius;
+        let recoil = -direction * mass * self.muzzle_speed;
+        let position = Point2::from(self.world_iso.translation.vector);
+        if let Some(body) = world.body_mut(body) {
+            body.apply_force_at_point(0, &recoil, &position, ForceType::Impulse, true);
+        }
+        self.reloading = self.cooldown;
+    }
+}
+
+impl Part for Cannon {
+    fn update(&mut self, world: &World<f64>) {
+        if let (Some(body), Some(local_iso)) = (self.body, self.local_iso) {
+            if let Some(body) = world.rigid_body(body) {
+                self.world_iso = body.position() * local_iso;
+            }
+        }
+        for projectile in &mut self.projectiles {
+            if let Some(body) = projectile.body.and_then(|body| world.rigid_body(body)) {
+                projectile.position = Point2::from(body.position().translation.vector);
+            }
+        }
+    }
+
+    fn drive(&mut self, world: &mut World<f64>, keys: &HashSet<Key>) {
+        let body = match self.body {
+            Some(body) => body,
+            None => return,
+        };
+        self.reloading = (self.reloading - world.timestep()).max(0.0);
+        let held = self.key.map_or(false, |key| keys.contains(&key));
+        let pressed = held && !self.key_held;
+        self.key_held = held;
+        if pressed && self.reloading <= 0.0 {
+            self.fire(world, body);
+        }
+    }
+
+    fn create(&mut self, world: &mut World<f64>) {
+        let body = match self.body {
+            Some(body) => body,
+            None => {
+                log::warn!("Cannon was never bound to a body.");
+                return;
+            }
+        };
+        if let Some(body) = world.rigid_body(body) {
+            self.local_iso = Some(body.position().inverse() * self.iso);
+            self.world_iso = self.iso;
+        }
+        self.reloading = 0.0;
+        self.key_held = false;
+    }
+
+    fn destroy(&mut self, world: &mut World<f64>) {
+        let projectiles = self
+            .projectiles
+            .drain(..)
+            .filter_map(|projectile| projectile.body)
+            .collect::<Vec<_>>();
+        world.remove_bodies(&projectiles);
+        self.body = None;
+        self.local_iso = None;
+    }
+
+    fn is_point_inside(&self, point: Vector2<f64>) -> bool {
+        (point - self.iso.translation.vector).norm() <= PICK_RADIUS
+    }
+
+    fn to_data(&self) -> PartData {
+        PartData::Cannon(self.clone())
+    }
+
+    fn name(&self) -> &'static str {
+        "Cannon"
+    }
+
+    fn selected(&self) -> bool {
+        self.selected
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+
+    fn z_order(&self) -> i32 {
+        self.z_order
+    }
+
+    fn set_z_order(&mut self, z_order: i32) {
+        self.z_order = z_order;
+    }
+
+    fn translate(&mut self, offset: Vector2<f64>) {
+        self.iso.translation.vector += offset;
+    }
+
+    fn rotate_about(&mut self, center: Point2<f64>, angle: f64) {
+        let rotation = UnitComplex::new(angle);
+        let position = center + rotation * (Point2::from(self.iso.translation.vector) - center);
+        self.iso = Isometry2::new(position.coords, self.iso.rotation.angle() + angle);
+    }
+
+    fn scale_about(&mut self, center: Point2<f64>, factor: f64) {
+        let position = Point2::from(self.iso.translation.vector);
+        self.iso.translation.vector = (center + (position - center) * factor).coords;
+    }
+
+    fn mirror_about(&mut self, center: Point2<f64>, mirror: Mirror) {
+        let position = mirror.point(center, Point2::from(self.iso.translation.vector));
+        let angle = mirror.direction(self.iso.rotation.angle());
+        self.iso = Isometry2::new(position.coords, angle);
+    }
+
+    fn bounds(&self) -> (Point2<f64>, Point2<f64>) {
+        let position = Point2::from(self.iso.translation.vector);
+        let extent = Vector2::repeat(PICK_RADIUS);
+        (position - extent, position + extent)
+    }
+
+    fn shapes(&self) -> Vec<usize> {
+        vec![self.shape]
+    }
+
+    fn remap_shapes(&mut self, map: &dyn Fn(usize) -> usize) {
+        self.shape = map(self.shape);
+    }
+
+    fn attach(&mut self, bodies: &[Option<BodyHandle>]) {
+        if let Some(body) = bodies[self.shape] {
+            self.bind(body);
+        }
+    }
+
+    fn as_cannon(&self) -> Option<&Cannon> {
+        Some(self)
+    }
+
+    fn as_cannon_mut(&mut self) -> Option<&mut Cannon> {
+        Some(self)
+    }
+}
@@ -1,32 +1,152 @@
-use crate::camera::Camera;
-use crate::part::{Part, Shape};
-use graphics::{Colored, Context, Transformed};
-use nalgebra::{Point2, Vector2};
+use crate::part::{Mirror, Part, PartData};
+use crate::util;
+use nalgebra::{Isometry2, Point2, UnitComplex, Vector2};
+use nphysics2d::algebra::{Force2, ForceType};
 use nphysics2d::joint::ConstraintHandle;
-use nphysics2d::object::BodyHandle;
+use nphysics2d::object::{BodyHandle, BodyPartHandle};
 use nphysics2d::world::World;
+use piston::input::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::f64::EPSILON;
 
-#[derive(Clone, Copy, Debug)]
+// how close to an anchor we have to be to pick a joint
+const PICK_RADIUS: f64 = 0.3;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum JointKind {
     Fixed,
     Prismatic,
     Revolute,
 }
 
-#[derive(Debug)]
+// drives a joint, for rotating joints the speed is in radians per second and the force is a torque,
+// for sliding joints it's in meters per second and the force is just that
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Motor {
+    pub enabled: bool,
+    pub speed: f64,
+    pub max_force: f64,
+    // without any keys the motor always runs forward
+    pub forward: Option<Key>,
+    pub backward: Option<Key>,
+    pub mode: ControlMode,
+}
+
+impl Motor {
+    pub fn is_bound(&self) -> bool {
+        self.forward.is_some() || self.backward.is_some()
+    }
+
+    pub fn is_bound_to(&self, key: Key) -> bool {
+        self.forward == Some(key) || self.backward == Some(key)
+    }
+}
+
+impl Default for Motor {
+    fn default() -> Self {
+        Motor {
+            enabled: false,
+            speed: 5.0,
+            max_force: 500.0,
+            forward: None,
+            backward: None,
+            mode: ControlMode::Hold,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ControlMode {
+    // runs while the key is held
+    Hold,
+    // a press starts it, the next one stops it
+    Toggle,
+}
+
+// what the keys are telling a motor to do during a run
+#[derive(Clone, Copy, Debug, Default)]
+struct Control {
+    // 1 runs forward, -1 backward and 0 leaves the joint free
+    direction: f64,
+    forward_held: bool,
+    backward_held: bool,
+}
+
+// how far a sliding joint can move from where it was built, negative values retract
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Travel {
+    pub enabled: bool,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Default for Travel {
+    fn default() -> Self {
+        Travel {
+            enabled: false,
+            lower: -1.0,
+            upper: 1.0,
+        }
+    }
+}
+
+// the anchors of a running joint, relative to each body
+#[derive(Clone, Copy, Debug)]
+struct Anchors {
+    local1: Point2<f64>,
+    local2: Point2<f64>,
+}
+
+// the shapes a joint connects, as indices into the part list
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
 struct Shapes {
-    shape1: Shape,
-    shape2: Shape,
+    shape1: usize,
+    shape2: usize,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Joint {
     kind: JointKind,
+    #[serde(skip)]
     handle: Option<ConstraintHandle>,
     shapes: Shapes,
+    // the bodies of our shapes, only known while running
+    #[serde(skip)]
+    bodies: Option<(BodyHandle, BodyHandle)>,
+    #[serde(skip)]
+    anchors: Option<Anchors>,
+    // where the anchors are in the world, while running or during a replay
+    #[serde(skip)]
+    world_anchors: Option<(Point2<f64>, Point2<f64>)>,
     anchor1: Point2<f64>,
     anchor2: Point2<f64>,
     axis: Vector2<f64>,
+    motor: Motor,
+    travel: Travel,
+    // jointed shapes pass through each other unless this is set
+    collide_connected: bool,
+    #[serde(skip)]
+    control: Control,
+    z_order: i32,
+    #[serde(skip)]
+    selected: bool,
+}
+
+// the handle and position of the first (and only) part of a body
+fn body_part(world: &World<f64>, body: BodyHandle) -> (BodyPartHandle, Isometry2<f64>) {
+    let part = world
+        .body(body)
+        .expect("Body for joint doesn't exist")
+        .part(0)
+        .expect("Part for joint doesn't exist");
+    (part.part_handle(), part.position())
+}
+
+fn apply_force(world: &mut World<f64>, body: BodyHandle, force: Force2<f64>) {
+    if let Some(body) = world.body_mut(body) {
+        body.apply_force(0, &force, ForceType::Force, true);
+    }
 }
 
 impl Joint {
@@ -38,57 +158,330 @@ impl Joint {
         self.anchor2
     }
 
+    // where the anchors are while running, or where they were placed otherwise
+    pub fn world_anchor1(&self) -> Point2<f64> {
+        self.world_anchors
+            .map_or(self.anchor1, |(anchor1, _)| anchor1)
+    }
+
+    pub fn world_anchor2(&self) -> Point2<f64> {
+        self.world_anchors
+            .map_or(self.anchor2, |(_, anchor2)| anchor2)
+    }
+
+    // used to pose joints without any physics, such as during a replay
+    pub fn set_world_anchors(&mut self, anchor1: Point2<f64>, anchor2: Point2<f64>) {
+        self.world_anchors = Some((anchor1, anchor2));
+    }
+
+    pub fn motor(&self) -> Motor {
+        self.motor
+    }
+
+    pub fn set_motor(&mut self, motor: Motor) {
+        self.motor = motor;
+    }
+
+    pub fn travel(&self) -> Travel {
+        self.travel
+    }
+
+    pub fn set_travel(&mut self, travel: Travel) {
+        self.travel = travel;
+    }
+
+    pub fn collide_connected(&self) -> bool {
+        self.collide_connected
+    }
+
+    pub fn set_collide_connected(&mut self, collide_connected: bool) {
+        self.collide_connected = collide_connected;
+    }
+
     pub fn kind(&self) -> JointKind {
         self.kind
     }
+
+    pub fn shape1(&self) -> usize {
+        self.shapes.shape1
+    }
+
+    pub fn shape2(&self) -> usize {
+        self.shapes.shape2
+    }
+
+    // moves the anchor on one of our shapes, for when that shape moves without the other one
+    pub fn move_anchor(&mut self, shape: usize, transform: &dyn Fn(Point2<f64>) -> Point2<f64>) {
+        if shape == self.shapes.shape1 {
+            self.anchor1 = transform(self.anchor1);
+        }
+        if shape == self.shapes.shape2 {
+            self.anchor2 = transform(self.anchor2);
+        }
+        // sliding joints slide along the line between their anchors
+        let axis = self.anchor2 - self.anchor1;
+        if self.kind == JointKind::Prismatic && axis.norm() > EPSILON {
+            self.axis = axis;
+        }
+    }
+
+    // works out which way the motor should run from the held keys
+    fn steer(&mut self, keys: &HashSet<Key>) -> f64 {
+        if !self.motor.is_bound() {
+            return 1.0;
+        }
+        let held = |key: Option<Key>| key.map_or(false, |key| keys.contains(&key));
+        let forward = held(self.motor.forward);
+        let backward = held(self.motor.backward);
+        let control = &mut self.control;
+        match self.motor.mode {
+            ControlMode::Hold => {
+                control.direction = match (forward, backward) {
+                    (true, false) => 1.0,
+                    (false, true) => -1.0,
+                    _ => 0.0,
+                };
+            }
+            ControlMode::Toggle => {
+                // only flip when the key goes down, not for as long as it's held
+                if forward && !control.forward_held {
+                    control.direction = if control.direction > 0.0 { 0.0 } else { 1.0 };
+                }
+                if backward && !control.backward_held {
+                    control.direction = if control.direction < 0.0 { 0.0 } else { -1.0 };
+                }
+            }
+        }
+        control.forward_held = forward;
+        control.backward_held = backward;
+        control.direction
+    }
+
+    // tells the joint which bodies to connect, this must be called before `create`
+    pub fn bind(&mut self, body1: BodyHandle, body2: BodyHandle) {
+        self.bodies = Some((body1, body2));
+    }
 }
 
 impl Part for Joint {
-    fn update(&mut self, world: &World<f64>) {}
+    fn update(&mut self, world: &World<f64>) {
+        if let (Some((body1, body2)), Some(anchors)) = (self.bodies, self.anchors) {
+            let (mut anchor1, mut anchor2) = (self.world_anchor1(), self.world_anchor2());
+            if let Some(body) = world.rigid_body(body1) {
+                anchor1 = body.position() * anchors.local1;
+            }
+            if let Some(body) = world.rigid_body(body2) {
+                anchor2 = body.position() * anchors.local2;
+            }
+            self.world_anchors = Some((anchor1, anchor2));
+        }
+    }
+
+    fn drive(&mut self, world: &mut World<f64>, keys: &HashSet<Key>) {
+        let (body1, body2) = match self.bodies {
+            Some(bodies) if self.motor.enabled => bodies,
+            _ => return,
+        };
+        let direction = self.steer(keys);
+        if direction == 0.0 {
+            return;
+        }
+        let speed = self.motor.speed * direction;
+        match self.kind {
+            JointKind::Revolute => {
+                // push towards the target speed, as hard as the motor is allowed to
+                let angular = |body| world.rigid_body(body).map_or(0.0, |b| b.velocity().angular);
+                let relative = angular(body2) - angular(body1);
+                let max = self.motor.max_force;
+                let torque = util::clamp((speed - relative) * max, -max, max);
+                apply_force(world, body1, Force2::new(Vector2::zeros(), -torque));
+                apply_force(world, body2, Force2::new(Vector2::zeros(), torque));
+            }
+            JointKind::Prismatic => {
+                // the axis turns with the first body
+                let rotation = match world.rigid_body(body1) {
+                    Some(body) => body.position().rotation,
+                    None => return,
+                };
+                let axis = rotation * self.axis.normalize();
+                let linear = |body| {
+                    world
+                        .rigid_body(body)
+                        .map_or(Vector2::zeros(), |b| b.velocity().linear)
+                };
+                let relative = (linear(body2) - linear(body1)).dot(&axis);
+                let max = self.motor.max_force;
+                let force = util::clamp((speed - relative) * max, -max, max);
+                apply_force(world, body1, Force2::new(axis * -force, 0.0));
+                apply_force(world, body2, Force2::new(axis * force, 0.0));
+            }
+            _ => {}
+        }
+    }
 
     fn create(&mut self, world: &mut World<f64>) {
+        let (body1, body2) = match self.bodies {
+            Some(bodies) => bodies,
+            None => {
+                log::warn!("Joint was never bound to any bodies.");
+                return;
+            }
+        };
+        let (body_part1, position1) = body_part(world, body1);
+        let (body_part2, position2) = body_part(world, body2);
+        // constraints want their anchors relative to each body
+        let anchors = Anchors {
+            local1: position1.inverse() * self.anchor1,
+            local2: position2.inverse() * self.anchor2,
+        };
+        self.anchors = Some(anchors);
+        self.world_anchors = Some((self.anchor1, self.anchor2));
+        self.control = Control::default();
         match self.kind {
             JointKind::Prismatic => {
                 use nalgebra::Unit;
                 use nphysics2d::joint::PrismaticConstraint;
-                let body_part1 = world
-                    .body(self.shapes.shape1.body_handle.expect("No body on shape1"))
-                    .expect("Body for shape1 doesn't exist")
-                    .part(0)
-                    .expect("Part for shape1 doesn't exist")
-                    .part_handle();
-                let body_part2 = world
-                    .body(self.shapes.shape2.body_handle.expect("No body on shape2"))
-                    .expect("Body for shape2 doesn't exist")
-                    .part(0)
-                    .expect("Part for shape2 doesn't exist")
-                    .part_handle();
-                let joint = PrismaticConstraint::new(
+                let mut joint = PrismaticConstraint::new(
                     body_part1,
                     body_part2,
-                    self.anchor1,
-                    Unit::new_normalize(self.axis),
-                    self.anchor2,
+                    anchors.local1,
+                    Unit::new_normalize(position1.inverse() * self.axis),
+                    anchors.local2,
+                );
+                if self.travel.enabled {
+                    // the offset starts out as how far apart the anchors are along the axis
+                    let offset = (self.anchor2 - self.anchor1).dot(&self.axis.normalize());
+                    joint.enable_min_offset(offset + self.travel.lower);
+                    joint.enable_max_offset(offset + self.travel.upper);
+                }
+                self.handle = Some(world.add_constraint(joint));
+            }
+            JointKind::Revolute => {
+                use nphysics2d::joint::RevoluteConstraint;
+                let joint =
+                    RevoluteConstraint::new(body_part1, body_part2, anchors.local1, anchors.local2);
+                self.handle = Some(world.add_constraint(joint));
+            }
+            JointKind::Fixed => {
+                use nphysics2d::joint::FixedConstraint;
+                // both frames start out at the anchor, and are kept there
+                let frame1 = position1.inverse() * Isometry2::new(self.anchor1.coords, 0.0);
+                let frame2 = position2.inverse() * Isometry2::new(self.anchor2.coords, 0.0);
+                let joint = FixedConstraint::new(
+                    body_part1,
+                    body_part2,
+                    Point2::from(frame1.translation.vector),
+                    frame1.rotation,
+                    Point2::from(frame2.translation.vector),
+                    frame2.rotation,
                 );
                 self.handle = Some(world.add_constraint(joint));
             }
-            _ => {}
         }
     }
 
     fn destroy(&mut self, world: &mut World<f64>) {
         if let Some(handle) = self.handle {
             world.remove_constraint(handle);
+            self.handle = None;
         }
+        self.bodies = None;
+        self.anchors = None;
+        self.world_anchors = None;
     }
 
     fn is_point_inside(&self, point: Vector2<f64>) -> bool {
-        false
+        let point = Point2::from(point);
+        nalgebra::distance(&point, &self.anchor1) <= PICK_RADIUS
+            || nalgebra::distance(&point, &self.anchor2) <= PICK_RADIUS
+    }
+
+    fn to_data(&self) -> PartData {
+        PartData::Joint(self.clone())
+    }
+
+    fn name(&self) -> &'static str {
+        match self.kind {
+            JointKind::Fixed => "Fixed joint",
+            JointKind::Prismatic => "Sliding joint",
+            JointKind::Revolute => "Rotating joint",
+        }
+    }
+
+    fn selected(&self) -> bool {
+        self.selected
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+
+    fn z_order(&self) -> i32 {
+        self.z_order
+    }
+
+    fn set_z_order(&mut self, z_order: i32) {
+        self.z_order = z_order;
+    }
+
+    fn translate(&mut self, offset: Vector2<f64>) {
+        self.anchor1 += offset;
+        self.anchor2 += offset;
+    }
+
+    fn rotate_about(&mut self, center: Point2<f64>, angle: f64) {
+        let rotation = UnitComplex::new(angle);
+        self.anchor1 = center + rotation * (self.anchor1 - center);
+        self.anchor2 = center + rotation * (self.anchor2 - center);
+        self.axis = rotation * self.axis;
+    }
+
+    fn scale_about(&mut self, center: Point2<f64>, factor: f64) {
+        self.anchor1 = center + (self.anchor1 - center) * factor;
+        self.anchor2 = center + (self.anchor2 - center) * factor;
+        self.axis *= factor;
+    }
+
+    fn mirror_about(&mut self, center: Point2<f64>, mirror: Mirror) {
+        self.anchor1 = mirror.point(center, self.anchor1);
+        self.anchor2 = mirror.point(center, self.anchor2);
+        self.axis = mirror.vector(self.axis);
+        // the mirrored wheel has to spin the other way to drive the same way
+        if self.kind == JointKind::Revolute {
+            self.motor.speed = -self.motor.speed;
+        }
+    }
+
+    fn bounds(&self) -> (Point2<f64>, Point2<f64>) {
+        (
+            nalgebra::inf(&self.anchor1, &self.anchor2),
+            nalgebra::sup(&self.anchor1, &self.anchor2),
+        )
+    }
+
+    fn shapes(&self) -> Vec<usize> {
+        vec![self.shapes.shape1, self.shapes.shape2]
+    }
+
+    fn remap_shapes(&mut self, map: &dyn Fn(usize) -> usize) {
+        self.shapes.shape1 = map(self.shapes.shape1);
+        self.shapes.shape2 = map(self.shapes.shape2);
+    }
+
+    fn attach(&mut self, bodies: &[Option<BodyHandle>]) {
+        if let (Some(body1), Some(body2)) = (bodies[self.shapes.shape1], bodies[self.shapes.shape2])
+        {
+            self.bind(body1, body2);
+        }
     }
 
     fn as_joint(&self) -> Option<&Joint> {
         Some(self)
     }
+
+    fn as_joint_mut(&mut self) -> Option<&mut Joint> {
+        Some(self)
+    }
 }
 
 pub struct JointBuilder {
@@ -100,7 +493,7 @@ pub struct JointBuilder {
 }
 
 impl JointBuilder {
-    pub fn fixed(shape1: Shape, shape2: Shape) -> Self {
+    pub fn fixed(shape1: usize, shape2: usize) -> Self {
         JointBuilder {
             kind: JointKind::Fixed,
             shapes: Shapes { shape1, shape2 },
@@ -110,18 +503,24 @@ impl JointBuilder {
         }
     }
 
-    pub fn prismatic(shape1: Shape, shape2: &Shape) -> Self {
-        // JointBuilder {
-        //     kind: JointKind::Prismatic,
-        //     shapes: Shapes {
-        //         shape1: shape1.clone(),
-        //         shape2: shape2.clone(),
-        //     },
-        //     anchor1: Point2::origin(),
-        //     anchor2: Point2::origin(),
-        //     axis: nalgebra::zero(),
-        // }
-        unimplemented!()
+    pub fn revolute(shape1: usize, shape2: usize) -> Self {
+        JointBuilder {
+            kind: JointKind::Revolute,
+            shapes: Shapes { shape1, shape2 },
+            anchor1: Point2::origin(),
+            anchor2: Point2::origin(),
+            axis: nalgebra::zero(),
+        }
+    }
+
+    pub fn prismatic(shape1: usize, shape2: usize) -> Self {
+        JointBuilder {
+            kind: JointKind::Prismatic,
+            shapes: Shapes { shape1, shape2 },
+            anchor1: Point2::origin(),
+            anchor2: Point2::origin(),
+            axis: nalgebra::zero(),
+        }
     }
 
     pub fn anchor1(&mut self, anchor1: Point2<f64>) -> &mut Self {
@@ -140,14 +539,22 @@ impl JointBuilder {
     }
 
     pub fn build(&mut self) -> Joint {
-        // Joint {
-        //     kind: self.kind,
-        //     handle: None,
-        //     shapes: self.shapes,
-        //     anchor1: self.anchor1,
-        //     anchor2: self.anchor2,
-        //     axis: self.axis,
-        // }
-        unimplemented!()
+        Joint {
+            kind: self.kind,
+            handle: None,
+            shapes: self.shapes,
+            bodies: None,
+            anchors: None,
+            world_anchors: None,
+            anchor1: self.anchor1,
+            anchor2: self.anchor2,
+            axis: self.axis,
+            motor: Motor::default(),
+            travel: Travel::default(),
+            collide_connected: false,
+            control: Control::default(),
+            z_order: 0,
+            selected: false,
+        }
     }
 }
@@ -1,24 +1,259 @@
-use nalgebra::Vector2;
+use nalgebra::{Point2, Vector2};
+use nphysics2d::object::BodyHandle;
 use nphysics2d::world::World;
+use piston::input::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::fmt::Debug;
 
+mod cannon;
 mod joint;
 mod shape;
+mod text;
+mod thruster;
 
-pub use self::joint::{Joint, JointBuilder, JointKind};
-pub use self::shape::{Shape, ShapeBuilder, ShapeKind};
+pub use self::cannon::Cannon;
+pub use self::joint::{ControlMode, Joint, JointBuilder, JointKind};
+pub use self::shape::{convex_hull, Material, Shape, ShapeBuilder, ShapeKind};
+pub use self::text::Text;
+pub use self::thruster::Thruster;
+
+// which way a mirror flips, horizontal swaps left and right
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mirror {
+    Horizontal,
+    Vertical,
+}
+
+impl Mirror {
+    pub fn vector(self, vector: Vector2<f64>) -> Vector2<f64> {
+        match self {
+            Mirror::Horizontal => Vector2::new(-vector.x, vector.y),
+            Mirror::Vertical => Vector2::new(vector.x, -vector.y),
+        }
+    }
+
+    pub fn point(self, center: Point2<f64>, point: Point2<f64>) -> Point2<f64> {
+        center + self.vector(point - center)
+    }
+
+    // the angle of a direction once it's been mirrored
+    pub fn direction(self, angle: f64) -> f64 {
+        match self {
+            Mirror::Horizontal => std::f64::consts::PI - angle,
+            Mirror::Vertical => -angle,
+        }
+    }
+}
 
 pub trait Part: Debug {
     fn update(&mut self, world: &World<f64>);
     fn create(&mut self, world: &mut World<f64>);
     fn destroy(&mut self, world: &mut World<f64>);
+
+    // applies any forces the part makes, called right before every step while running
+    fn drive(&mut self, _world: &mut World<f64>, _keys: &HashSet<Key>) {}
+
     fn is_point_inside(&self, point: Vector2<f64>) -> bool;
 
+    // the plain data of this part, used for saving and copying
+    fn to_data(&self) -> PartData;
+
+    // what the part is called in the gui
+    fn name(&self) -> &'static str;
+
+    fn selected(&self) -> bool;
+    fn set_selected(&mut self, selected: bool);
+
+    // parts with a higher z order are drawn on top, and picked first
+    fn z_order(&self) -> i32;
+    fn set_z_order(&mut self, z_order: i32);
+
+    // moves the part while editing
+    fn translate(&mut self, offset: Vector2<f64>);
+
+    // rotates the part around a point while editing
+    fn rotate_about(&mut self, center: Point2<f64>, angle: f64);
+
+    // scales the part, and its distance from a point, while editing
+    fn scale_about(&mut self, center: Point2<f64>, factor: f64);
+
+    // flips the part, and where it is, around a point while editing
+    fn mirror_about(&mut self, center: Point2<f64>, mirror: Mirror);
+
+    // the smallest and largest factor the part can be scaled by and stay inside of the limits
+    fn scale_limits(&self) -> (f64, f64) {
+        (0.0, std::f64::INFINITY)
+    }
+
+    // the smallest box containing the part while editing, as (min, max)
+    fn bounds(&self) -> (Point2<f64>, Point2<f64>);
+
+    // the indices of the shapes this part is attached to, if it goes away so do we
+    fn shapes(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    // parts that refer to shapes use this to follow them when the part list changes
+    fn remap_shapes(&mut self, _map: &dyn Fn(usize) -> usize) {}
+
+    // hands parts the bodies of the shapes they are attached to, indexed like the part list,
+    // this is called right before `create`
+    fn attach(&mut self, _bodies: &[Option<BodyHandle>]) {}
+
     fn as_shape(&self) -> Option<&Shape> {
         None
     }
 
+    fn as_shape_mut(&mut self) -> Option<&mut Shape> {
+        None
+    }
+
     fn as_joint(&self) -> Option<&Joint> {
         None
     }
+
+    fn as_joint_mut(&mut self) -> Option<&mut Joint> {
+        None
+    }
+
+    fn as_thruster(&self) -> Option<&Thruster> {
+        None
+    }
+
+    fn as_thruster_mut(&mut self) -> Option<&mut Thruster> {
+        None
+    }
+
+    fn as_cannon(&self) -> Option<&Cannon> {
+        None
+    }
+
+    fn as_cannon_mut(&mut self) -> Option<&mut Cannon> {
+        None
+    }
+
+    fn as_text(&self) -> Option<&Text> {
+        None
+    }
+
+    fn as_text_mut(&mut self) -> Option<&mut Text> {
+        None
+    }
+}
+
+// copies of the parts at `indices`, plus anything attached only to those shapes, parts attached to
+// a shape that's left out are left out too, and shape indices are remapped to the new list
+pub fn extract(parts: &[Box<dyn Part>], indices: &[usize]) -> Vec<Box<dyn Part>> {
+    let kept_shapes = indices
+        .iter()
+        .cloned()
+        .filter(|&index| parts[index].as_shape().is_some())
+        .collect::<HashSet<_>>();
+    let kept = (0..parts.len())
+        .filter(|index| {
+            let shapes = parts[*index].shapes();
+            if shapes.is_empty() {
+                indices.contains(index)
+            } else {
+                shapes.iter().all(|shape| kept_shapes.contains(shape))
+            }
+        })
+        .collect::<Vec<_>>();
+    kept.iter()
+        .map(|&index| {
+            let mut part = parts[index].to_data().into_part();
+            part.remap_shapes(&|shape| {
+                kept.iter()
+                    .position(|&kept_index| kept_index == shape)
+                    .expect("Kept part is attached to a shape that wasn't kept")
+            });
+            part
+        })
+        .collect()
+}
+
+// everything we need to rebuild a part, without any of the physics state
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum PartData {
+    Shape(Shape),
+    Joint(Joint),
+    Thruster(Thruster),
+    Cannon(Cannon),
+    Text(Text),
+}
+
+impl PartData {
+    pub fn into_part(self) -> Box<dyn Part> {
+        match self {
+            PartData::Shape(shape) => Box::new(shape),
+            PartData::Joint(joint) => Box::new(joint),
+            PartData::Thruster(thruster) => Box::new(thruster),
+            PartData::Cannon(cannon) => Box::new(cannon),
+            PartData::Text(text) => Box::new(text),
+        }
+    }
+
+    // see `Part::shapes`
+    pub fn shapes(&self) -> Vec<usize> {
+        match self {
+            PartData::Shape(shape) => shape.shapes(),
+            PartData::Joint(joint) => joint.shapes(),
+            PartData::Thruster(thruster) => thruster.shapes(),
+            PartData::Cannon(cannon) => cannon.shapes(),
+            PartData::Text(text) => text.shapes(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn mirrors_vectors() {
+        let vector = Vector2::new(1.0, 2.0);
+        assert_eq!(Mirror::Horizontal.vector(vector), Vector2::new(-1.0, 2.0));
+        assert_eq!(Mirror::Vertical.vector(vector), Vector2::new(1.0, -2.0));
+    }
+
+    #[test]
+    fn mirrors_points_about_a_center() {
+        let center = Point2::new(1.0, 1.0);
+        let point = Point2::new(3.0, 4.0);
+        assert_eq!(
+            Mirror::Horizontal.point(center, point),
+            Point2::new(-1.0, 4.0)
+        );
+        assert_eq!(
+            Mirror::Vertical.point(center, point),
+            Point2::new(3.0, -2.0)
+        );
+        assert_eq!(Mirror::Vertical.point(center, center), center);
+    }
+
+    #[test]
+    fn mirrors_directions() {
+        let angle = PI / 6.0;
+        let direction = |angle: f64| Vector2::new(angle.cos(), angle.sin());
+        for &mirror in &[Mirror::Horizontal, Mirror::Vertical] {
+            let mirrored = direction(mirror.direction(angle));
+            assert!((mirrored - mirror.vector(direction(angle))).norm() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn extract_keeps_order_and_remaps() {
+        let parts: Vec<Box<dyn Part>> = vec![
+            Box::new(ShapeBuilder::circle(1.0).build()),
+            Box::new(ShapeBuilder::circle(1.0).build()),
+            Box::new(JointBuilder::fixed(1, 3).build()),
+            Box::new(ShapeBuilder::circle(1.0).build()),
+        ];
+        let extracted = extract(&parts, &[3, 1]);
+        assert_eq!(extracted.len(), 3);
+        assert!(extracted[1].as_joint().is_some());
+        assert_eq!(extracted[1].shapes(), vec![0, 2]);
+    }
 }
@@ -1,11 +1,17 @@
-use crate::part::Part;
+use crate::collision;
+use crate::limits;
+use crate::part::{Mirror, Part, PartData};
 use graphics::color;
-use nalgebra::{Isometry2, Vector2};
-use ncollide2d::shape::{Ball, Cuboid, ShapeHandle};
+use nalgebra::{Isometry2, Point2, UnitComplex, Vector2};
+use ncollide2d::shape::{Ball, ConvexPolygon, Cuboid, ShapeHandle};
+use ncollide2d::world::CollisionGroups;
+use nphysics2d::material::{BasicMaterial, MaterialHandle};
 use nphysics2d::object::{BodyHandle, BodyStatus, ColliderDesc, RigidBodyDesc};
 use nphysics2d::world::World;
+use serde::{Deserialize, Serialize};
+use std::f64::EPSILON;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum ShapeKind {
     Circle {
         radius: f64,
@@ -19,17 +25,102 @@ pub enum ShapeKind {
         p2: Vector2<f64>,
         p3: Vector2<f64>,
     },
+    // the points go around a convex hull
+    Polygon {
+        points: Vec<Vector2<f64>>,
+    },
+}
+
+// the corners of the smallest convex polygon around some points, or nothing if they don't make one
+pub fn convex_hull(points: &[Vector2<f64>]) -> Option<Vec<Vector2<f64>>> {
+    convex_polygon(points)
+        .map(|polygon| polygon.points().iter().map(|point| point.coords).collect())
+        .filter(|hull: &Vec<_>| hull.len() >= 3)
+}
+
+// ncollide panics on fewer than 3 different points, or on points that are all in a line
+fn convex_polygon(points: &[Vector2<f64>]) -> Option<ConvexPolygon<f64>> {
+    if !has_area(points) {
+        return None;
+    }
+    let points = points.iter().map(|&p| Point2::from(p)).collect::<Vec<_>>();
+    ConvexPolygon::try_from_points(&points)
+}
+
+// true when some three of the points aren't in a line
+fn has_area(points: &[Vector2<f64>]) -> bool {
+    let first = match points.first() {
+        Some(&first) => first,
+        None => return false,
+    };
+    let second = match points.iter().find(|&&p| (p - first).norm() > EPSILON) {
+        Some(&second) => second,
+        None => return false,
+    };
+    let edge = second - first;
+    points.iter().any(|&p| {
+        let offset = p - first;
+        (edge.x * offset.y - edge.y * offset.x).abs() > EPSILON
+    })
 }
 
-#[derive(Debug)]
+// inside when the point is on the same side of every edge, this works for either winding
+fn is_inside_convex(points: &[Vector2<f64>], point: Point2<f64>) -> bool {
+    let mut has_negative = false;
+    let mut has_positive = false;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        let side = (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x);
+        has_negative |= side < 0.0;
+        has_positive |= side > 0.0;
+    }
+    !(has_negative && has_positive)
+}
+
+// how a shape behaves when it touches things
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Material {
+    pub density: f64,
+    pub friction: f64,
+    // how bouncy it is, 0 doesn't bounce and 1 bounces back as fast as it came
+    pub restitution: f64,
+    // shapes that don't collide pass through everything, but still have mass
+    pub collides: bool,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            density: 1.0,
+            friction: 0.5,
+            restitution: 0.0,
+            collides: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Shape {
     kind: ShapeKind,
     iso: Isometry2<f64>,
+    // the physics state is never saved
+    #[serde(skip, default = "Isometry2::identity")]
     world_iso: Isometry2<f64>,
+    #[serde(skip)]
     pub(super) body_handle: Option<BodyHandle>,
     color: [f32; 4],
     ground: bool,
+    material: Material,
+    // an index into the collision layers
+    layer: usize,
+    // the camera follows shapes with this set while running
+    camera_focus: bool,
+    z_order: i32,
+    // worked out from the layers right before `create`
+    #[serde(skip)]
+    collision_groups: CollisionGroups,
     // cool rust 2018 thingy
+    #[serde(skip)]
     pub(super) selected: bool,
 }
 
@@ -42,13 +133,65 @@ impl Shape {
         self.world_iso
     }
 
-    pub fn kind(&self) -> ShapeKind {
-        self.kind
+    // used to pose shapes without any physics, such as during a replay
+    pub fn set_world_iso(&mut self, world_iso: Isometry2<f64>) {
+        self.world_iso = world_iso;
+    }
+
+    pub fn kind(&self) -> &ShapeKind {
+        &self.kind
     }
 
     pub fn color(&self) -> [f32; 4] {
         self.color
     }
+
+    pub fn ground(&self) -> bool {
+        self.ground
+    }
+
+    pub fn body_handle(&self) -> Option<BodyHandle> {
+        self.body_handle
+    }
+
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    pub fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    pub fn layer(&self) -> usize {
+        self.layer
+    }
+
+    pub fn set_layer(&mut self, layer: usize) {
+        self.layer = layer;
+    }
+
+    pub fn camera_focus(&self) -> bool {
+        self.camera_focus
+    }
+
+    pub fn set_camera_focus(&mut self, camera_focus: bool) {
+        self.camera_focus = camera_focus;
+    }
+
+    // a box around the shape where it is while running, big enough for it to be turned any way
+    pub fn world_bounds(&self) -> (Point2<f64>, Point2<f64>) {
+        let (min, max) = self.bounds();
+        let position = Point2::from(self.iso.translation.vector);
+        let reach = (max - min).norm() / 2.0 + (nalgebra::center(&min, &max) - position).norm();
+        let center = Point2::from(self.world_iso.translation.vector);
+        let extent = Vector2::repeat(reach);
+        (center - extent, center + extent)
+    }
+
+    // this must be called before `create`
+    pub fn set_collision_groups(&mut self, collision_groups: CollisionGroups) {
+        self.collision_groups = collision_groups;
+    }
 }
 
 impl Part for Shape {
@@ -62,22 +205,40 @@ impl Part for Shape {
 
     fn create(&mut self, world: &mut World<f64>) {
         let shape_handle = match self.kind {
-            ShapeKind::Circle { radius } => ShapeHandle::new(Ball::new(radius)),
+            ShapeKind::Circle { radius } => Some(ShapeHandle::new(Ball::new(radius))),
             ShapeKind::Rectangle {
                 half_width,
                 half_height,
-            } => ShapeHandle::new(Cuboid::new(Vector2::new(half_width, half_height))),
+            } => Some(ShapeHandle::new(Cuboid::new(Vector2::new(
+                half_width,
+                half_height,
+            )))),
             ShapeKind::Triangle { p1, p2, p3 } => {
-                use nalgebra::Point2;
-                use ncollide2d::shape::ConvexPolygon;
-                let points = &[p1, p2, p3]
-                    .iter()
-                    .map(|p| Point2::new(p.x, p.y))
-                    .collect::<Vec<_>>();
-                ShapeHandle::new(ConvexPolygon::try_from_points(&points).expect("FIXME"))
+                convex_polygon(&[p1, p2, p3]).map(ShapeHandle::new)
+            }
+            ShapeKind::Polygon { ref points } => convex_polygon(points).map(ShapeHandle::new),
+        };
+        let shape_handle = match shape_handle {
+            Some(shape_handle) => shape_handle,
+            None => {
+                // a hand written file can have points all in a line, that isn't worth crashing over
+                log::error!("{} doesn't have an area, leaving it out.", self.name());
+                return;
             }
         };
-        let collider = ColliderDesc::new(shape_handle).density(1.0);
+        let material = self.material;
+        let collision_groups = if material.collides {
+            self.collision_groups
+        } else {
+            collision::without_collisions(self.collision_groups)
+        };
+        let collider = ColliderDesc::new(shape_handle)
+            .density(material.density)
+            .material(MaterialHandle::new(BasicMaterial::new(
+                material.restitution,
+                material.friction,
+            )))
+            .collision_groups(collision_groups);
         let rigid_body = RigidBodyDesc::new()
             .collider(&collider)
             .status(if self.ground {
@@ -98,12 +259,193 @@ impl Part for Shape {
     }
 
     fn is_point_inside(&self, point: Vector2<f64>) -> bool {
-        true
+        // much easier to test in the shape's own space
+        let local = self.iso.inverse() * Point2::from(point);
+        match self.kind {
+            ShapeKind::Circle { radius } => local.coords.norm() <= radius,
+            ShapeKind::Rectangle {
+                half_width,
+                half_height,
+            } => local.x.abs() <= half_width.abs() && local.y.abs() <= half_height.abs(),
+            ShapeKind::Triangle { p1, p2, p3 } => is_inside_convex(&[p1, p2, p3], local),
+            ShapeKind::Polygon { ref points } => is_inside_convex(points, local),
+        }
+    }
+
+    fn to_data(&self) -> PartData {
+        PartData::Shape(self.clone())
+    }
+
+    fn name(&self) -> &'static str {
+        match self.kind {
+            ShapeKind::Circle { .. } => "Circle",
+            ShapeKind::Rectangle { .. } => "Rectangle",
+            ShapeKind::Triangle { .. } => "Triangle",
+            ShapeKind::Polygon { .. } => "Polygon",
+        }
+    }
+
+    fn selected(&self) -> bool {
+        self.selected
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+
+    fn z_order(&self) -> i32 {
+        self.z_order
+    }
+
+    fn set_z_order(&mut self, z_order: i32) {
+        self.z_order = z_order;
+    }
+
+    fn translate(&mut self, offset: Vector2<f64>) {
+        self.iso.translation.vector += offset;
+    }
+
+    fn rotate_about(&mut self, center: Point2<f64>, angle: f64) {
+        let position =
+            center.coords + UnitComplex::new(angle) * (self.iso.translation.vector - center.coords);
+        self.iso = Isometry2::new(position, self.iso.rotation.angle() + angle);
+    }
+
+    fn scale_about(&mut self, center: Point2<f64>, factor: f64) {
+        self.iso.translation.vector =
+            center.coords + (self.iso.translation.vector - center.coords) * factor;
+        self.kind = match self.kind {
+            ShapeKind::Circle { radius } => ShapeKind::Circle {
+                radius: radius * factor,
+            },
+            ShapeKind::Rectangle {
+                half_width,
+                half_height,
+            } => ShapeKind::Rectangle {
+                half_width: half_width * factor,
+                half_height: half_height * factor,
+            },
+            ShapeKind::Triangle { p1, p2, p3 } => ShapeKind::Triangle {
+                p1: p1 * factor,
+                p2: p2 * factor,
+                p3: p3 * factor,
+            },
+            ShapeKind::Polygon { ref points } => ShapeKind::Polygon {
+                points: points.iter().map(|point| point * factor).collect(),
+            },
+        };
+    }
+
+    fn mirror_about(&mut self, center: Point2<f64>, mirror: Mirror) {
+        let position = mirror.point(center, Point2::from(self.iso.translation.vector));
+        // mirroring a rotated shape is the same as rotating the mirrored shape the other way
+        self.iso = Isometry2::new(position.coords, -self.iso.rotation.angle());
+        match self.kind {
+            ShapeKind::Triangle { p1, p2, p3 } => {
+                // flipping reverses the winding, so swap two points to keep the triangle convex
+                self.kind = ShapeKind::Triangle {
+                    p1: mirror.vector(p1),
+                    p2: mirror.vector(p3),
+                    p3: mirror.vector(p2),
+                };
+            }
+            ShapeKind::Polygon { ref mut points } => {
+                // the same goes for polygons, they go back around the other way
+                points.reverse();
+                for point in points.iter_mut() {
+                    *point = mirror.vector(*point);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn scale_limits(&self) -> (f64, f64) {
+        // (current size, smallest size, largest size) of every dimension
+        let sizes = match self.kind {
+            ShapeKind::Circle { radius } => {
+                vec![(radius, limits::MIN_CIRCLE_SIZE, limits::MAX_CIRCLE_SIZE)]
+            }
+            ShapeKind::Rectangle {
+                half_width,
+                half_height,
+            } => vec![
+                (
+                    half_width.abs(),
+                    limits::MIN_RECTANGLE_SIZE,
+                    limits::MAX_RECTANGLE_SIZE,
+                ),
+                (
+                    half_height.abs(),
+                    limits::MIN_RECTANGLE_SIZE,
+                    limits::MAX_RECTANGLE_SIZE,
+                ),
+            ],
+            ShapeKind::Triangle { p1, p2, p3 } => vec![(
+                p1.norm().max(p2.norm()).max(p3.norm()),
+                limits::MIN_TRIANGLE_SIZE,
+                limits::MAX_TRIANGLE_SIZE,
+            )],
+            ShapeKind::Polygon { ref points } => vec![(
+                points.iter().map(|point| point.norm()).fold(0.0, f64::max),
+                limits::MIN_POLYGON_SIZE,
+                limits::MAX_POLYGON_SIZE,
+            )],
+        };
+        let min = sizes
+            .iter()
+            .map(|&(size, min, _)| min / size)
+            .fold(0.0, f64::max);
+        let max = sizes
+            .iter()
+            .map(|&(size, _, max)| max / size)
+            .fold(std::f64::INFINITY, f64::min);
+        // shapes that are already past the limits, like the ground, stay the size they are
+        if min > max {
+            (1.0, 1.0)
+        } else {
+            (min, max)
+        }
+    }
+
+    fn bounds(&self) -> (Point2<f64>, Point2<f64>) {
+        let points = match self.kind {
+            ShapeKind::Circle { radius } => {
+                let center = self.iso * Point2::origin();
+                let extent = Vector2::new(radius, radius);
+                return (center - extent, center + extent);
+            }
+            ShapeKind::Rectangle {
+                half_width,
+                half_height,
+            } => vec![
+                Point2::new(-half_width, -half_height),
+                Point2::new(half_width, -half_height),
+                Point2::new(half_width, half_height),
+                Point2::new(-half_width, half_height),
+            ],
+            ShapeKind::Triangle { p1, p2, p3 } => {
+                vec![Point2::from(p1), Point2::from(p2), Point2::from(p3)]
+            }
+            ShapeKind::Polygon { ref points } => points.iter().map(|&p| Point2::from(p)).collect(),
+        };
+        let mut min = Point2::new(std::f64::MAX, std::f64::MAX);
+        let mut max = Point2::new(std::f64::MIN, std::f64::MIN);
+        for point in points {
+            let point = self.iso * point;
+            min = nalgebra::inf(&min, &point);
+            max = nalgebra::sup(&max, &point);
+        }
+        (min, max)
     }
 
     fn as_shape(&self) -> Option<&Shape> {
         Some(self)
     }
+
+    fn as_shape_mut(&mut self) -> Option<&mut Shape> {
+        Some(self)
+    }
 }
 
 pub struct ShapeBuilder {
@@ -152,6 +494,18 @@ impl ShapeBuilder {
         }
     }
 
+    // the points should already go around a convex hull, see `convex_hull`
+    pub fn polygon(points: Vec<Vector2<f64>>) -> Self {
+        ShapeBuilder {
+            kind: ShapeKind::Polygon { points },
+            position: nalgebra::zero(),
+            rotation: 0.0,
+            color: color::WHITE,
+            ground: false,
+            selected: false,
+        }
+    }
+
     pub fn position(&mut self, position: Vector2<f64>) -> &mut Self {
         self.position = position;
         self
@@ -185,13 +539,65 @@ impl ShapeBuilder {
 
     pub fn build(&self) -> Shape {
         Shape {
-            kind: self.kind,
+            kind: self.kind.clone(),
             iso: Isometry2::new(self.position, self.rotation),
             world_iso: Isometry2::identity(),
             body_handle: None,
             color: self.color,
             ground: self.ground,
+            material: Material::default(),
+            layer: 0,
+            camera_focus: false,
+            collision_groups: CollisionGroups::default(),
+            z_order: 0,
             selected: self.selected,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> Vec<Vector2<f64>> {
+        vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(2.0, 0.0),
+            Vector2::new(2.0, 2.0),
+            Vector2::new(0.0, 2.0),
+        ]
+    }
+
+    #[test]
+    fn convex_hull_drops_inside_points() {
+        let mut points = square();
+        points.push(Vector2::new(1.0, 1.0));
+        let hull = convex_hull(&points).unwrap();
+        assert_eq!(hull.len(), 4);
+        assert!(square().iter().all(|point| hull.contains(point)));
+    }
+
+    #[test]
+    fn convex_hull_needs_area() {
+        assert_eq!(convex_hull(&[]), None);
+        assert_eq!(convex_hull(&[Vector2::new(1.0, 1.0); 3]), None);
+        let line = [
+            Vector2::new(0.0, 0.0),
+            Vector2::new(1.0, 1.0),
+            Vector2::new(2.0, 2.0),
+        ];
+        assert_eq!(convex_hull(&line), None);
+    }
+
+    #[test]
+    fn inside_convex_with_either_winding() {
+        let mut points = square();
+        for _ in 0..2 {
+            assert!(is_inside_convex(&points, Point2::new(1.0, 1.0)));
+            assert!(is_inside_convex(&points, Point2::new(2.0, 1.0)));
+            assert!(!is_inside_convex(&points, Point2::new(3.0, 1.0)));
+            assert!(!is_inside_convex(&points, Point2::new(-0.5, -0.5)));
+            points.reverse();
+        }
+    }
+}
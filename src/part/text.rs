@@ -0,0 +1,215 @@
+use crate::limits;
+use crate::part::{Mirror, Part, PartData};
+use nalgebra::{Isometry2, Point2, UnitComplex, Vector2};
+use nphysics2d::object::BodyHandle;
+use nphysics2d::world::World;
+use serde::{Deserialize, Serialize};
+
+// we don't have the font while editing, so guess how wide a character is compared to its height
+const CHARACTER_WIDTH: f64 = 0.6;
+
+// a label in the world, the position is where the text starts on the baseline
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Text {
+    content: String,
+    iso: Isometry2<f64>,
+    // how tall the text is, in world units
+    size: f64,
+    color: [f32; 4],
+    // the shape we move with while running, if any
+    shape: Option<usize>,
+    #[serde(skip)]
+    body: Option<BodyHandle>,
+    // where we sit on the body, only known while running
+    #[serde(skip)]
+    local_iso: Option<Isometry2<f64>>,
+    #[serde(skip, default = "Isometry2::identity")]
+    world_iso: Isometry2<f64>,
+    z_order: i32,
+    #[serde(skip)]
+    selected: bool,
+}
+
+impl Text {
+    pub fn new(content: String, position: Point2<f64>, shape: Option<usize>) -> Self {
+        let iso = Isometry2::new(position.coords, 0.0);
+        Text {
+            content,
+            iso,
+            size: 0.5,
+            color: [1.0, 1.0, 1.0, 1.0],
+            shape,
+            body: None,
+            local_iso: None,
+            world_iso: iso,
+            z_order: 0,
+            selected: false,
+        }
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn set_content(&mut self, content: String) {
+        self.content = content;
+    }
+
+    pub fn iso(&self) -> Isometry2<f64> {
+        self.iso
+    }
+
+    pub fn world_iso(&self) -> Isometry2<f64> {
+        self.world_iso
+    }
+
+    // used to pose text without any physics, such as during a replay
+    pub fn set_world_iso(&mut self, world_iso: Isometry2<f64>) {
+        self.world_iso = world_iso;
+    }
+
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    pub fn set_size(&mut self, size: f64) {
+        self.size = size;
+    }
+
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    pub fn set_color(&mut self, color: [f32; 4]) {
+        self.color = color;
+    }
+
+    pub fn shape(&self) -> Option<usize> {
+        self.shape
+    }
+
+    pub fn set_shape(&mut self, shape: Option<usize>) {
+        self.shape = shape;
+    }
+}
+
+impl Part for Text {
+    fn update(&mut self, world: &World<f64>) {
+        if let (Some(body), Some(local_iso)) = (self.body, self.local_iso) {
+            if let Some(body) = world.rigid_body(body) {
+                self.world_iso = body.position() * local_iso;
+            }
+        }
+    }
+
+    fn create(&mut self, world: &mut World<f64>) {
+        self.world_iso = self.iso;
+        if let Some(body) = self.body.and_then(|body| world.rigid_body(body)) {
+            self.local_iso = Some(body.position().inverse() * self.iso);
+        }
+    }
+
+    fn destroy(&mut self, _world: &mut World<f64>) {
+        self.body = None;
+        self.local_iso = None;
+    }
+
+    fn is_point_inside(&self, point: Vector2<f64>) -> bool {
+        let local = self.iso.inverse() * Point2::from(point);
+        let width = self.content.chars().count() as f64 * self.size * CHARACTER_WIDTH;
+        local.x >= 0.0 && local.x <= width && local.y <= 0.0 && local.y >= -self.size
+    }
+
+    fn to_data(&self) -> PartData {
+        PartData::Text(self.clone())
+    }
+
+    fn name(&self) -> &'static str {
+        "Text"
+    }
+
+    fn selected(&self) -> bool {
+        self.selected
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+
+    fn z_order(&self) -> i32 {
+        self.z_order
+    }
+
+    fn set_z_order(&mut self, z_order: i32) {
+        self.z_order = z_order;
+    }
+
+    fn translate(&mut self, offset: Vector2<f64>) {
+        self.iso.translation.vector += offset;
+    }
+
+    fn rotate_about(&mut self, center: Point2<f64>, angle: f64) {
+        let rotation = UnitComplex::new(angle);
+        let position = center + rotation * (Point2::from(self.iso.translation.vector) - center);
+        self.iso = Isometry2::new(position.coords, self.iso.rotation.angle() + angle);
+    }
+
+    fn scale_about(&mut self, center: Point2<f64>, factor: f64) {
+        let position = Point2::from(self.iso.translation.vector);
+        self.iso.translation.vector = (center + (position - center) * factor).coords;
+        self.size *= factor;
+    }
+
+    fn mirror_about(&mut self, center: Point2<f64>, mirror: Mirror) {
+        // the text itself stays readable, only where it sits is mirrored
+        let (min, max) = self.bounds();
+        let middle = nalgebra::center(&min, &max);
+        let offset = mirror.point(center, middle) - middle;
+        self.iso.translation.vector += offset;
+    }
+
+    fn scale_limits(&self) -> (f64, f64) {
+        (
+            limits::MIN_TEXT_SIZE / self.size,
+            limits::MAX_TEXT_SIZE / self.size,
+        )
+    }
+
+    fn bounds(&self) -> (Point2<f64>, Point2<f64>) {
+        let width = self.content.chars().count() as f64 * self.size * CHARACTER_WIDTH;
+        let corners = [
+            Point2::new(0.0, 0.0),
+            Point2::new(width, 0.0),
+            Point2::new(0.0, -self.size),
+            Point2::new(width, -self.size),
+        ];
+        let mut min = self.iso * corners[0];
+        let mut max = min;
+        for corner in &corners[1..] {
+            let corner = self.iso * corner;
+            min = nalgebra::inf(&min, &corner);
+            max = nalgebra::sup(&max, &corner);
+        }
+        (min, max)
+    }
+
+    fn shapes(&self) -> Vec<usize> {
+        self.shape.into_iter().collect()
+    }
+
+    fn remap_shapes(&mut self, map: &dyn Fn(usize) -> usize) {
+        self.shape = self.shape.map(map);
+    }
+
+    fn attach(&mut self, bodies: &[Option<BodyHandle>]) {
+        self.body = self.shape.and_then(|shape| bodies[shape]);
+    }
+
+    fn as_text(&self) -> Option<&Text> {
+        Some(self)
+    }
+
+    fn as_text_mut(&mut self) -> Option<&mut Text> {
+        Some(self)
+    }
+}
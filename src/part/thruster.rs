@@ -0,0 +1,212 @@
+use crate::part::{Mirror, Part, PartData};
+use nalgebra::{Isometry2, Point2, UnitComplex, Vector2};
+use nphysics2d::algebra::ForceType;
+use nphysics2d::object::BodyHandle;
+use nphysics2d::world::World;
+use piston::input::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// how close to a thruster we have to be to pick it
+const PICK_RADIUS: f64 = 0.3;
+
+// pushes the shape it's attached to, along its direction
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Thruster {
+    // the shape we push, as an index into the part list
+    shape: usize,
+    // where we are and which way we push, the thrust goes along the local x axis
+    iso: Isometry2<f64>,
+    strength: f64,
+    // without a key the thruster always fires
+    key: Option<Key>,
+    // the body of our shape, only known while running
+    #[serde(skip)]
+    body: Option<BodyHandle>,
+    // where we sit on the body, only known while running
+    #[serde(skip)]
+    local_iso: Option<Isometry2<f64>>,
+    #[serde(skip, default = "Isometry2::identity")]
+    world_iso: Isometry2<f64>,
+    #[serde(skip)]
+    firing: bool,
+    z_order: i32,
+    #[serde(skip)]
+    selected: bool,
+}
+
+impl Thruster {
+    pub fn new(shape: usize, position: Point2<f64>, angle: f64) -> Self {
+        let iso = Isometry2::new(position.coords, angle);
+        Thruster {
+            shape,
+            iso,
+            strength: 50.0,
+            key: None,
+            body: None,
+            local_iso: None,
+            world_iso: iso,
+            firing: false,
+            z_order: 0,
+            selected: false,
+        }
+    }
+
+    pub fn iso(&self) -> Isometry2<f64> {
+        self.iso
+    }
+
+    pub fn world_iso(&self) -> Isometry2<f64> {
+        self.world_iso
+    }
+
+    // used to pose thrusters without any physics, such as during a replay
+    pub fn set_world_iso(&mut self, world_iso: Isometry2<f64>) {
+        self.world_iso = world_iso;
+    }
+
+    pub fn strength(&self) -> f64 {
+        self.strength
+    }
+
+    pub fn set_strength(&mut self, strength: f64) {
+        self.strength = strength;
+    }
+
+    pub fn key(&self) -> Option<Key> {
+        self.key
+    }
+
+    pub fn set_key(&mut self, key: Option<Key>) {
+        self.key = key;
+    }
+
+    pub fn firing(&self) -> bool {
+        self.firing
+    }
+
+    // tells the thruster which body to push, this must be called before `create`
+    pub fn bind(&mut self, body: BodyHandle) {
+        self.body = Some(body);
+    }
+}
+
+impl Part for Thruster {
+    fn update(&mut self, world: &World<f64>) {
+        if let (Some(body), Some(local_iso)) = (self.body, self.local_iso) {
+            if let Some(body) = world.rigid_body(body) {
+                self.world_iso = body.position() * local_iso;
+            }
+        }
+    }
+
+    fn drive(&mut self, world: &mut World<f64>, keys: &HashSet<Key>) {
+        self.firing = self.key.map_or(true, |key| keys.contains(&key));
+        let body = match self.body {
+            Some(body) if self.firing => body,
+            _ => return,
+        };
+        let force = self.world_iso.rotation * Vector2::new(self.strength, 0.0);
+        let point = Point2::from(self.world_iso.translation.vector);
+        if let Some(body) = world.body_mut(body) {
+            body.apply_force_at_point(0, &force, &point, ForceType::Force, true);
+        }
+    }
+
+    fn create(&mut self, world: &mut World<f64>) {
+        let body = match self.body {
+            Some(body) => body,
+            None => {
+                log::warn!("Thruster was never bound to a body.");
+                return;
+            }
+        };
+        if let Some(body) = world.rigid_body(body) {
+            self.local_iso = Some(body.position().inverse() * self.iso);
+            self.world_iso = self.iso;
+        }
+    }
+
+    fn destroy(&mut self, _world: &mut World<f64>) {
+        self.body = None;
+        self.local_iso = None;
+        self.firing = false;
+    }
+
+    fn is_point_inside(&self, point: Vector2<f64>) -> bool {
+        (point - self.iso.translation.vector).norm() <= PICK_RADIUS
+    }
+
+    fn to_data(&self) -> PartData {
+        PartData::Thruster(self.clone())
+    }
+
+    fn name(&self) -> &'static str {
+        "Thruster"
+    }
+
+    fn selected(&self) -> bool {
+        self.selected
+    }
+
+    fn set_selected(&mut self, selected: bool) {
+        self.selected = selected;
+    }
+
+    fn z_order(&self) -> i32 {
+        self.z_order
+    }
+
+    fn set_z_order(&mut self, z_order: i32) {
+        self.z_order = z_order;
+    }
+
+    fn translate(&mut self, offset: Vector2<f64>) {
+        self.iso.translation.vector += offset;
+    }
+
+    fn rotate_about(&mut self, center: Point2<f64>, angle: f64) {
+        let rotation = UnitComplex::new(angle);
+        let position = center + rotation * (Point2::from(self.iso.translation.vector) - center);
+        self.iso = Isometry2::new(position.coords, self.iso.rotation.angle() + angle);
+    }
+
+    fn scale_about(&mut self, center: Point2<f64>, factor: f64) {
+        let position = Point2::from(self.iso.translation.vector);
+        self.iso.translation.vector = (center + (position - center) * factor).coords;
+    }
+
+    fn mirror_about(&mut self, center: Point2<f64>, mirror: Mirror) {
+        let position = mirror.point(center, Point2::from(self.iso.translation.vector));
+        let angle = mirror.direction(self.iso.rotation.angle());
+        self.iso = Isometry2::new(position.coords, angle);
+    }
+
+    fn bounds(&self) -> (Point2<f64>, Point2<f64>) {
+        let position = Point2::from(self.iso.translation.vector);
+        let extent = Vector2::repeat(PICK_RADIUS);
+        (position - extent, position + extent)
+    }
+
+    fn shapes(&self) -> Vec<usize> {
+        vec![self.shape]
+    }
+
+    fn remap_shapes(&mut self, map: &dyn Fn(usize) -> usize) {
+        self.shape = map(self.shape);
+    }
+
+    fn attach(&mut self, bodies: &[Option<BodyHandle>]) {
+        if let Some(body) = bodies[self.shape] {
+            self.bind(body);
+        }
+    }
+
+    fn as_thruster(&self) -> Option<&Thruster> {
+        Some(self)
+    }
+
+    fn as_thruster_mut(&mut self) -> Option<&mut Thruster> {
+        Some(self)
+    }
+}
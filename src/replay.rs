@@ -0,0 +1,346 @@
+use crate::file::{self, FileError};
+use crate::part::{Part, PartData};
+use crate::robot;
+use crate::util;
+use nalgebra::{Isometry2, Point2};
+use piston::input::Key;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+// every replay file starts with this, so we don't try to load random files
+const HEADER: [u8; 8] = *b"AWFULRPL";
+// bump this whenever the layout of a frame or a part changes
+const FORMAT_VERSION: u32 = 14;
+
+// everything that happened during a single step of the world
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Frame {
+    // the pose of every part, in the same order as the parts of the replay
+    poses: Vec<Isometry2<f64>>,
+    // where the anchors of every joint were, in the order the joints come in the parts
+    anchors: Vec<(Point2<f64>, Point2<f64>)>,
+    // where the projectiles of every cannon were, in the order the cannons come in the parts
+    projectiles: Vec<Vec<Point2<f64>>>,
+    // the keys held down during this step
+    keys: Vec<Key>,
+}
+
+impl Frame {
+    pub fn poses(&self) -> &[Isometry2<f64>] {
+        &self.poses
+    }
+
+    pub fn anchors(&self) -> &[(Point2<f64>, Point2<f64>)] {
+        &self.anchors
+    }
+
+    pub fn projectiles(&self) -> &[Vec<Point2<f64>>] {
+        &self.projectiles
+    }
+
+    pub fn keys(&self) -> &[Key] {
+        &self.keys
+    }
+}
+
+// a recording of a single run, from start to stop
+#[derive(Debug)]
+pub struct Replay {
+    parts: Vec<PartData>,
+    frames: Vec<Frame>,
+}
+
+impl Replay {
+    // starts a new recording of the given parts
+    pub fn new(parts: &[Box<dyn Part>]) -> Self {
+        Replay {
+            parts: parts.iter().map(|part| part.to_data()).collect(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, parts: &[Box<dyn Part>], keys: Vec<Key>) {
+        let poses = parts
+            .iter()
+            .map(|part| {
+                if let Some(shape) = part.as_shape() {
+                    shape.world_iso()
+                } else if let Some(thruster) = part.as_thruster() {
+                    thruster.world_iso()
+                } else if let Some(cannon) = part.as_cannon() {
+                    cannon.world_iso()
+                } else if let Some(text) = part.as_text() {
+                    text.world_iso()
+                } else {
+                    Isometry2::identity()
+                }
+            })
+            .collect();
+        let anchors = parts
+            .iter()
+            .filter_map(|part| part.as_joint())
+            .map(|joint| (joint.world_anchor1(), joint.world_anchor2()))
+            .collect();
+        let projectiles = parts
+            .iter()
+            .filter_map(|part| part.as_cannon())
+            .map(|cannon| {
+                cannon
+                    .projectiles()
+                    .iter()
+                    .map(|projectile| projectile.position())
+                    .collect()
+            })
+            .collect();
+        self.frames.push(Frame {
+            poses,
+            anchors,
+            projectiles,
+            keys,
+        });
+    }
+
+    pub fn parts(&self) -> Vec<Box<dyn Part>> {
+        self.parts
+            .iter()
+            .cloned()
+            .map(PartData::into_part)
+            .collect()
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn frame(&self, index: usize) -> Option<&Frame> {
+        self.frames.get(index)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), FileError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        file::write_header(&mut writer, &HEADER, FORMAT_VERSION)?;
+        bincode::serialize_into(&mut writer, &self.parts)?;
+        bincode::serialize_into(&mut writer, &self.frames)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        file::read_header(&mut reader, &HEADER, FORMAT_VERSION)?;
+        let parts: Vec<PartData> = bincode::deserialize_from(&mut reader)?;
+        robot::validate(&parts)?;
+        let frames: Vec<Frame> = bincode::deserialize_from(&mut reader)?;
+        // every frame needs a pose for every part, anchors for every joint and projectiles for
+        // every cannon
+        let joints = parts
+            .iter()
+            .filter(|part| match part {
+                PartData::Joint(_) => true,
+                _ => false,
+            })
+            .count();
+        let cannons = parts
+            .iter()
+            .filter(|part| match part {
+                PartData::Cannon(_) => true,
+                _ => false,
+            })
+            .count();
+        if let Some(i) = frames.iter().position(|f| {
+            f.poses.len() != parts.len()
+                || f.anchors.len() != joints
+                || f.projectiles.len() != cannons
+        }) {
+            return Err(FileError::BadFrame(i));
+        }
+        Ok(Replay { parts, frames })
+    }
+}
+
+// plays back a replay, without ever touching the physics world
+pub struct Playback {
+    replay: Replay,
+    // the parts being shown, posed from the current frame
+    parts: Vec<Box<dyn Part>>,
+    // the current frame, fractional so we can play slower than real time
+    position: f64,
+    // how many frames we advance per update
+    speed: f64,
+    paused: bool,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        let mut playback = Playback {
+            parts: replay.parts(),
+            replay,
+            position: 0.0,
+            speed: 1.0,
+            paused: false,
+        };
+        playback.apply_frame();
+        playback
+    }
+
+    pub fn update(&mut self) {
+        if self.paused {
+            return;
+        }
+        let last = self.last_frame();
+        self.position = (self.position + self.speed).min(last);
+        // stop at the end instead of looping
+        if self.position >= last {
+            self.paused = true;
+        }
+        self.apply_frame();
+    }
+
+    pub fn parts(&self) -> &[Box<dyn Part>] {
+        &self.parts
+    }
+
+    pub fn frame(&self) -> usize {
+        self.position as usize
+    }
+
+    pub fn last_frame(&self) -> f64 {
+        self.replay.frame_count().saturating_sub(1) as f64
+    }
+
+    pub fn seek(&mut self, frame: f64) {
+        self.position = util::clamp(frame, 0.0, self.last_frame());
+        self.apply_frame();
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed;
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        // starting again from the end rewinds
+        if self.paused && self.position >= self.last_frame() {
+            self.position = 0.0;
+        }
+        self.paused = !self.paused;
+    }
+
+    // the keys that were held at the current frame
+    pub fn keys(&self) -> &[Key] {
+        match self.replay.frame(self.frame()) {
+            Some(frame) => frame.keys(),
+            None => &[],
+        }
+    }
+
+    fn apply_frame(&mut self) {
+        let frame = match self.replay.frame(self.frame()) {
+            Some(frame) => frame,
+            None => return,
+        };
+        for (part, pose) in self.parts.iter_mut().zip(frame.poses()) {
+            if let Some(shape) = part.as_shape_mut() {
+                shape.set_world_iso(*pose);
+            } else if let Some(thruster) = part.as_thruster_mut() {
+                thruster.set_world_iso(*pose);
+            } else if let Some(cannon) = part.as_cannon_mut() {
+                cannon.set_world_iso(*pose);
+            } else if let Some(text) = part.as_text_mut() {
+                text.set_world_iso(*pose);
+            }
+        }
+        let joints = self.parts.iter_mut().filter_map(|part| part.as_joint_mut());
+        for (joint, &(anchor1, anchor2)) in joints.zip(frame.anchors()) {
+            joint.set_world_anchors(anchor1, anchor2);
+        }
+        let cannons = self
+            .parts
+            .iter_mut()
+            .filter_map(|part| part.as_cannon_mut());
+        for (cannon, projectiles) in cannons.zip(frame.projectiles()) {
+            cannon.set_projectile_positions(projectiles);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part::{JointBuilder, ShapeBuilder};
+    use nalgebra::Vector2;
+
+    // a replay of a circle that moves one meter right every frame
+    fn replay(frames: usize) -> Replay {
+        let mut parts: Vec<Box<dyn Part>> = vec![
+            Box::new(ShapeBuilder::circle(1.0).build()),
+            Box::new(ShapeBuilder::circle(1.0).build()),
+            Box::new(JointBuilder::revolute(0, 1).build()),
+        ];
+        let mut replay = Replay::new(&parts);
+        for frame in 0..frames {
+            let x = frame as f64;
+            parts[0]
+                .as_shape_mut()
+                .unwrap()
+                .set_world_iso(Isometry2::new(Vector2::new(x, 0.0), 0.0));
+            parts[2]
+                .as_joint_mut()
+                .unwrap()
+                .set_world_anchors(Point2::new(x, 1.0), Point2::new(x, 2.0));
+            let keys = if frame == 1 { vec![Key::Space] } else { vec![] };
+            replay.record(&parts, keys);
+        }
+        replay
+    }
+
+    fn circle_x(playback: &Playback) -> f64 {
+        playback.parts()[0]
+            .as_shape()
+            .unwrap()
+            .world_iso()
+            .translation
+            .vector
+            .x
+    }
+
+    #[test]
+    fn seek_poses_parts() {
+        let mut playback = Playback::new(replay(3));
+        playback.seek(1.0);
+        assert_eq!(playback.frame(), 1);
+        assert_eq!(circle_x(&playback), 1.0);
+        assert_eq!(playback.keys(), &[Key::Space]);
+        let joint = playback.parts()[2].as_joint().unwrap();
+        assert_eq!(joint.world_anchor1(), Point2::new(1.0, 1.0));
+        assert_eq!(joint.world_anchor2(), Point2::new(1.0, 2.0));
+    }
+
+    #[test]
+    fn seek_is_clamped() {
+        let mut playback = Playback::new(replay(3));
+        assert_eq!(playback.last_frame(), 2.0);
+        playback.seek(10.0);
+        assert_eq!(playback.frame(), 2);
+        assert_eq!(circle_x(&playback), 2.0);
+        playback.seek(-10.0);
+        assert_eq!(playback.frame(), 0);
+        assert_eq!(circle_x(&playback), 0.0);
+    }
+
+    #[test]
+    fn empty_replays_stay_at_the_start() {
+        let mut playback = Playback::new(replay(0));
+        playback.seek(5.0);
+        assert_eq!(playback.frame(), 0);
+        assert!(playback.keys().is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::collision::{self, Layers};
+use crate::file::{self, FileError};
+use crate::part::{Part, PartData};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+// every robot file starts with this, so we don't try to load random files
+const HEADER: [u8; 8] = *b"AWFULBOT";
+// bump this whenever the layout of a part changes
+const FORMAT_VERSION: u32 = 12;
+
+// a robot is a list of parts, joints refer to shapes by their index in this list, and the
+// collision layers its shapes are on
+#[derive(Debug)]
+pub struct Robot {
+    parts: Vec<PartData>,
+    layers: Layers,
+}
+
+impl Robot {
+    pub fn from_parts(parts: &[Box<dyn Part>], layers: &Layers) -> Self {
+        Robot {
+            parts: parts.iter().map(|part| part.to_data()).collect(),
+            layers: layers.clone(),
+        }
+    }
+
+    pub fn layers(&self) -> &Layers {
+        &self.layers
+    }
+
+    pub fn into_parts(self) -> Vec<Box<dyn Part>> {
+        self.parts.into_iter().map(PartData::into_part).collect()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), FileError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        file::write_header(&mut writer, &HEADER, FORMAT_VERSION)?;
+        bincode::serialize_into(&mut writer, &self.parts)?;
+        bincode::serialize_into(&mut writer, &self.layers)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, FileError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        file::read_header(&mut reader, &HEADER, FORMAT_VERSION)?;
+        let robot = Robot {
+            parts: bincode::deserialize_from(&mut reader)?,
+            layers: bincode::deserialize_from(&mut reader)?,
+        };
+        validate(&robot.parts)?;
+        Ok(robot)
+    }
+}
+
+// makes sure every part attached to shapes points at shapes that exist, and every shape is on a
+// layer that exists
+pub fn validate(parts: &[PartData]) -> Result<(), FileError> {
+    let is_shape = |index: usize| match parts.get(index) {
+        Some(PartData::Shape(_)) => true,
+        _ => false,
+    };
+    for (i, part) in parts.iter().enumerate() {
+        if !part.shapes().into_iter().all(is_shape) {
+            return Err(FileError::BadReference(i));
+        }
+        if let PartData::Shape(shape) = part {
+            if shape.layer() >= collision::LAYER_COUNT {
+                return Err(FileError::BadLayer(i));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part::{JointBuilder, ShapeBuilder};
+    use nalgebra::{Point2, Vector2};
+    use std::path::PathBuf;
+
+    // somewhere to write files that won't clash with other test runs
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("awfulbots-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let parts: Vec<Box<dyn Part>> = vec![
+            Box::new(ShapeBuilder::circle(1.0).build()),
+            Box::new(
+                ShapeBuilder::rectangle(1.0, 2.0)
+                    .position(Vector2::new(3.0, 0.0))
+                    .build(),
+            ),
+            Box::new(
+                JointBuilder::revolute(0, 1)
+                    .anchor1(Point2::new(1.0, 0.0))
+                    .anchor2(Point2::new(1.0, 0.0))
+                    .build(),
+            ),
+        ];
+        let mut layers = Layers::default();
+        layers.set_name(2, "Wheels".to_owned());
+        layers.set_collides(0, 2, false);
+        let path = temp_path("round-trip.bot");
+        Robot::from_parts(&parts, &layers).save(&path).unwrap();
+        let robot = Robot::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let robot = robot.unwrap();
+        assert_eq!(robot.layers().name(2), "Wheels");
+        assert!(!robot.layers().collides(2, 0));
+        assert!(robot.layers().collides(1, 2));
+        let loaded = robot.into_parts();
+        assert_eq!(loaded.len(), parts.len());
+        for (loaded, part) in loaded.iter().zip(&parts) {
+            assert_eq!(loaded.name(), part.name());
+            assert_eq!(loaded.bounds(), part.bounds());
+            assert_eq!(loaded.shapes(), part.shapes());
+        }
+    }
+
+    #[test]
+    fn load_rejects_other_versions() {
+        let path = temp_path("old-version.bot");
+        let mut writer = File::create(&path).unwrap();
+        file::write_header(&mut writer, &HEADER, FORMAT_VERSION - 1).unwrap();
+        drop(writer);
+        let result = Robot::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        match result {
+            Err(FileError::UnsupportedVersion { found, expected }) => {
+                assert_eq!(found, FORMAT_VERSION - 1);
+                assert_eq!(expected, FORMAT_VERSION);
+            }
+            other => panic!("expected an unsupported version, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn load_rejects_other_kinds_of_files() {
+        let path = temp_path("replay.bot");
+        let mut writer = File::create(&path).unwrap();
+        file::write_header(&mut writer, b"AWFULRPL", FORMAT_VERSION).unwrap();
+        drop(writer);
+        let result = Robot::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        match result {
+            Err(FileError::WrongKind) => {}
+            other => panic!("expected the wrong kind of file, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate_rejects_joints_to_missing_shapes() {
+        let parts = vec![
+            ShapeBuilder::circle(1.0).build().to_data(),
+            JointBuilder::fixed(0, 5).build().to_data(),
+        ];
+        match validate(&parts) {
+            Err(FileError::BadReference(1)) => {}
+            other => panic!("expected a bad reference, got {:?}", other),
+        }
+    }
+}
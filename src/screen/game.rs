@@ -1,22 +1,66 @@
 use crate::action::{Action, ActionKind};
 use crate::camera::Camera;
+use crate::challenge::{Challenge, ChallengeState, Outcome};
+use crate::clipboard::Clipboard;
+use crate::collision::{self, Layers};
 use crate::gui::Ids;
+use crate::history::{self, History};
 use crate::limits;
-use crate::part::{JointBuilder, Part, ShapeBuilder};
+use crate::part::{
+    self, convex_hull, Cannon, ControlMode, JointBuilder, JointKind, Material, Mirror, Part,
+    ShapeBuilder, Text, Thruster,
+};
+use crate::replay::{Playback, Replay};
+use crate::robot::Robot;
 use crate::screen::Screen;
 use crate::util;
-use crate::visualizer::Visualizer;
+use crate::visualizer::{self, Handles, Visualizer};
 use conrod_core::color::{self, Color};
 use conrod_core::widget::{self, Widget};
 use conrod_core::{Colorable, Labelable, Positionable, Sizeable};
 use conrod_core::{Scalar, UiCell};
 use graphics::{Context, Transformed};
-use nalgebra::{Point2, Vector2};
+use nalgebra::{Point2, UnitComplex, Vector2};
 use nphysics2d::joint::{ConstraintHandle, MouseConstraint};
 use nphysics2d::object::{BodyHandle, BodyPartHandle};
 use nphysics2d::world::World;
 use opengl_graphics::{GlGraphics, GlyphCache};
 use piston::input::{Key, MouseButton};
+use std::collections::HashSet;
+use std::f64::EPSILON;
+
+// what dragging the mouse is doing to the selection
+#[derive(Clone, Copy, Debug)]
+enum Drag {
+    Move {
+        last: Point2<f64>,
+    },
+    Rotate {
+        center: Point2<f64>,
+        last_angle: f64,
+    },
+    Resize {
+        center: Point2<f64>,
+        last_distance: f64,
+    },
+}
+
+// what text can be colored with
+const TEXT_COLORS: [[f32; 4]; 6] = [
+    [1.0, 1.0, 1.0, 1.0],
+    [0.0, 0.0, 0.0, 1.0],
+    [1.0, 0.3, 0.3, 1.0],
+    [0.3, 1.0, 0.3, 1.0],
+    [0.3, 0.5, 1.0, 1.0],
+    [1.0, 1.0, 0.3, 1.0],
+];
+
+// which of a motor's keys is being bound
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum KeySlot {
+    Forward,
+    Backward,
+}
 
 pub struct GameScreen {
     camera: Camera,
@@ -28,12 +72,55 @@ pub struct GameScreen {
     grabbed_object_constraint: Option<ConstraintHandle>,
     middle_mouse_down: bool,
     running: bool,
-    dragging_part: bool,
+    dragging: Option<Drag>,
+    // the first bit of movement during a drag is recorded, so it can be undone
+    drag_moved: bool,
     selected_parts: Vec<usize>,
     action: Action,
     visualizer: Visualizer,
+    // the file used for saving and loading
+    file_name: String,
+    // the last thing that went right or wrong, shown in the gui
+    status: String,
+    // parts waiting to be placed with a click
+    placing: Vec<Box<dyn Part>>,
+    held_keys: HashSet<Key>,
+    // the joint whose next key press becomes one of its motor keys
+    binding: Option<(usize, KeySlot)>,
+    // the run currently being recorded
+    recording: Option<Replay>,
+    // the last finished run, kept around so it can be saved
+    last_replay: Option<Replay>,
+    // when this is set we are watching a replay instead of simulating
+    playback: Option<Playback>,
+    challenge: Option<ChallengeState>,
+    history: History,
+    show_settings: bool,
+    show_scale: bool,
+    show_layers: bool,
+    // the camera zooms out to keep every camera focus in view while running
+    zoom_to_fit: bool,
+    // where the camera was before it started following, so stopping goes back to it
+    edit_camera: Option<Camera>,
+    // which collision layers collide with each other, saved with the robot
+    layers: Layers,
+    // what the scale tool scales the selection by
+    scale_factor: f64,
+    clipboard: Clipboard,
+    // where the rubber band box selection started, in world space
+    selection_start: Option<Point2<f64>>,
+    // so clicking on the gui doesn't also click on the world behind it
+    mouse_over_gui: bool,
+    // so typing into the gui doesn't also trigger shortcuts
+    typing: bool,
 }
 
+const DEFAULT_FILE_NAME: &str = "robot.awb";
+// how close to the first corner of a polygon a click has to be to finish it, on screen
+const POLYGON_CLOSE_DISTANCE: f64 = 10.0;
+// how fast the keys move the camera, in pixels per second
+const PAN_SPEED: f64 = 600.0;
+
 impl GameScreen {
     pub fn new(camera: Camera) -> Self {
         let mut world = World::new();
@@ -81,19 +168,77 @@ impl GameScreen {
             grabbed_object_constraint: None,
             middle_mouse_down: false,
             running: false,
-            dragging_part: false,
+            dragging: None,
+            drag_moved: false,
             selected_parts: Vec::new(),
             action: Action::default(),
             visualizer: Visualizer::new(),
+            file_name: DEFAULT_FILE_NAME.to_owned(),
+            status: String::new(),
+            placing: Vec::new(),
+            held_keys: HashSet::new(),
+            binding: None,
+            recording: None,
+            last_replay: None,
+            playback: None,
+            challenge: None,
+            history: History::new(history::DEFAULT_DEPTH),
+            show_settings: false,
+            show_scale: false,
+            show_layers: false,
+            zoom_to_fit: false,
+            edit_camera: None,
+            layers: Layers::default(),
+            scale_factor: 2.0,
+            clipboard: Clipboard::default(),
+            selection_start: None,
+            mouse_over_gui: false,
+            typing: false,
         }
     }
 
-    fn zoom_in(&mut self) {
-        self.camera.set_zoom(self.camera.zoom() * 4.0 / 3.0)
+    // `anchor` is the point on the screen that stays put
+    fn zoom_in(&mut self, anchor: Vector2<f64>) {
+        self.camera.zoom_about(anchor, 4.0 / 3.0)
     }
 
-    fn zoom_out(&mut self) {
-        self.camera.set_zoom(self.camera.zoom() * 3.0 / 4.0)
+    fn zoom_out(&mut self, anchor: Vector2<f64>) {
+        self.camera.zoom_about(anchor, 3.0 / 4.0)
+    }
+
+    fn screen_center(&self) -> Vector2<f64> {
+        self.camera.size() / 2.0
+    }
+
+    // moves the camera for as long as the arrow keys (or WASD) are held
+    fn pan(&mut self, dt: f64) {
+        if self.typing || self.ctrl_held() {
+            return;
+        }
+        let held = |keys: &[Key]| {
+            keys.iter().any(|&key| {
+                // keys driving the robot shouldn't also move the camera
+                self.held_keys.contains(&key) && !(self.running && self.is_bound(key))
+            })
+        };
+        let mut direction = Vector2::zeros();
+        if held(&[Key::A, Key::Left]) {
+            direction.x -= 1.0;
+        }
+        if held(&[Key::D, Key::Right]) {
+            direction.x += 1.0;
+        }
+        if held(&[Key::W, Key::Up]) {
+            direction.y -= 1.0;
+        }
+        if held(&[Key::S, Key::Down]) {
+            direction.y += 1.0;
+        }
+        if direction != Vector2::zeros() {
+            // the speed is in screen space, so panning feels the same at any zoom
+            self.camera
+                .trans(&(direction * PAN_SPEED * dt / self.camera.zoom()));
+        }
     }
 
     fn start(&mut self) {
@@ -101,9 +246,43 @@ impl GameScreen {
             log::warn!("Game already running.");
             return;
         }
+        if self.playback.is_some() {
+            log::warn!("Can't start while watching a replay.");
+            return;
+        }
+        self.cancel_action();
         self.running = true;
+        self.recording = Some(Replay::new(&self.parts));
+        if let Some(challenge) = &mut self.challenge {
+            challenge.reset();
+        }
+        let groups = self.layers.collision_groups(&self.parts);
+        for (part, &groups) in self.parts.iter_mut().zip(&groups) {
+            if let Some(shape) = part.as_shape_mut() {
+                shape.set_collision_groups(groups);
+            }
+        }
+        // shapes have to exist before the parts attached to them
         for part in &mut self.parts {
-            part.create(&mut self.world);
+            if part.shapes().is_empty() {
+                part.create(&mut self.world);
+            }
+        }
+        let bodies = self
+            .parts
+            .iter()
+            .map(|part| part.as_shape().and_then(|s| s.body_handle()))
+            .collect::<Vec<_>>();
+        // registering it again replaces the filter from the last run
+        let filter = collision::JointFilter::new(&self.parts, &bodies);
+        self.world
+            .collider_world_mut()
+            .register_broad_phase_pair_filter("joints", filter);
+        for part in &mut self.parts {
+            if !part.shapes().is_empty() {
+                part.attach(&bodies);
+                part.create(&mut self.world);
+            }
         }
     }
 
@@ -116,36 +295,1110 @@ impl GameScreen {
         for part in &mut self.parts {
             part.destroy(&mut self.world);
         }
+        self.last_replay = self.recording.take();
+        if let Some(camera) = self.edit_camera.take() {
+            self.camera = camera;
+        }
+    }
+
+    // the box around every shape the camera should follow, if there are any
+    fn focus_bounds(&self) -> Option<(Point2<f64>, Point2<f64>)> {
+        util::merge_bounds(
+            self.parts
+                .iter()
+                .filter_map(|part| part.as_shape())
+                .filter(|shape| shape.camera_focus())
+                .map(|shape| shape.world_bounds()),
+        )
+    }
+
+    fn follow_focus(&mut self, dt: f64) {
+        let (min, max) = match self.focus_bounds() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let edit_camera = *self.edit_camera.get_or_insert(self.camera);
+        self.camera
+            .follow(dt, min, max, self.zoom_to_fit, edit_camera.zoom());
+    }
+
+    // like the material, camera focus can be changed on many shapes at once
+    fn set_camera_focus(&mut self, camera_focus: bool) {
+        self.record("Change camera focus");
+        for &index in &self.selected_parts {
+            if let Some(shape) = self.parts[index].as_shape_mut() {
+                shape.set_camera_focus(camera_focus);
+            }
+        }
+    }
+
+    // stops whatever is being made, and drops any parts that were waiting to be placed
+    fn cancel_action(&mut self) {
+        self.action.reset();
+        self.placing.clear();
     }
 
     fn start_action(&mut self, kind: ActionKind) {
-        if kind == ActionKind::None || self.running {
+        if kind == ActionKind::None || self.running || self.playback.is_some() {
             log::info!("What is that you think you're doing?");
             return;
         }
+        if let Some(challenge) = &self.challenge {
+            if !challenge.challenge().allows(kind) {
+                self.status = "That isn't allowed in this challenge".to_owned();
+                return;
+            }
+        }
         log::info!("Starting action: {:?}", kind);
-        self.action.reset();
+        self.cancel_action();
         self.action.set_kind(kind);
     }
 
-    fn get_part_at(&self, point: Vector2<f64>) -> Option<usize> {
+    fn save_robot(&mut self) {
+        match Robot::from_parts(&self.parts, &self.layers).save(&self.file_name) {
+            Ok(()) => {
+                log::info!("Saved robot to {}", self.file_name);
+                self.status = format!("Saved {}", self.file_name);
+            }
+            Err(e) => {
+                log::error!("Unable to save robot to {}: {}", self.file_name, e);
+                self.status = format!("Unable to save {}: {}", self.file_name, e);
+            }
+        }
+    }
+
+    fn load_robot(&mut self) {
+        let robot = match Robot::load(&self.file_name) {
+            Ok(robot) => robot,
+            Err(e) => {
+                log::error!("Unable to load robot from {}: {}", self.file_name, e);
+                self.status = format!("Unable to load {}: {}", self.file_name, e);
+                return;
+            }
+        };
+        // loading always puts us back into edit mode
+        if self.running {
+            self.stop();
+        }
+        self.cancel_action();
+        self.selected_parts.clear();
+        // a robot is loaded on its own, use "Load and insert" to bring one into a challenge
+        self.challenge = None;
+        self.history.clear();
+        self.layers = robot.layers().clone();
+        self.parts = robot.into_parts();
+        log::info!("Loaded robot from {}", self.file_name);
+        self.status = format!("Loaded {}", self.file_name);
+    }
+
+    fn load_and_insert_robot(&mut self) {
+        match Robot::load(&self.file_name) {
+            Ok(robot) => {
+                self.start_action(ActionKind::PlacingParts);
+                if self.action.kind() == ActionKind::PlacingParts {
+                    // its ground and static terrain would pile up on ours, so only the robot itself comes along
+                    let parts = robot.into_parts();
+                    let keep = (0..parts.len())
+                        .filter(|&index| !parts[index].as_shape().map_or(false, |s| s.ground()))
+                        .collect::<Vec<_>>();
+                    self.placing = part::extract(&parts, &keep);
+                    if self.placing.is_empty() {
+                        self.cancel_action();
+                        self.status = format!("{} has nothing to place", self.file_name);
+                    } else {
+                        self.status = format!("Click to place {}", self.file_name);
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("Unable to load robot from {}: {}", self.file_name, e);
+                self.status = format!("Unable to load {}: {}", self.file_name, e);
+            }
+        }
+    }
+
+    // adds the waiting parts centered on `at`, and selects them, returns whether they were placed
+    fn place_parts(&mut self, at: Point2<f64>) -> bool {
+        let (min, max) = match util::bounds(self.placing.iter().map(|part| part.as_ref())) {
+            Some(bounds) => bounds,
+            None => return false,
+        };
+        if let Some(challenge) = &self.challenge {
+            let challenge = challenge.challenge();
+            if !self
+                .placing
+                .iter()
+                .all(|part| challenge.allows_part(part.as_ref()))
+            {
+                // they won't be allowed anywhere else either, so stop placing them
+                self.status = "Some of those parts aren't allowed in this challenge".to_owned();
+                self.cancel_action();
+                return false;
+            }
+        }
+        let offset = at - nalgebra::center(&min, &max);
+        // the parts are only taken once they fit, so they can be placed somewhere else instead
+        if !self.can_build_at(min + offset, max + offset) {
+            return false;
+        }
+        let mut parts = std::mem::replace(&mut self.placing, Vec::new());
+        let base = self.parts.len();
+        // placed parts go on top, but keep their order among themselves
+        let lowest = parts.iter().map(|part| part.z_order()).min().unwrap_or(0);
+        let z_offset = self.top_z_order() + 1 - lowest;
+        for part in &mut parts {
+            part.translate(offset);
+            part.remap_shapes(&|index| index + base);
+            part.set_z_order(part.z_order() + z_offset);
+        }
+        self.record("Place parts");
+        self.parts.extend(parts);
+        self.select((base..self.parts.len()).collect());
+        true
+    }
+
+    fn select(&mut self, indices: Vec<usize>) {
+        for &index in &self.selected_parts {
+            self.parts[index].set_selected(false);
+        }
+        for &index in &indices {
+            self.parts[index].set_selected(true);
+        }
+        self.selected_parts = indices;
+    }
+
+    fn shift_held(&self) -> bool {
+        self.held_keys.contains(&Key::LShift) || self.held_keys.contains(&Key::RShift)
+    }
+
+    fn ctrl_held(&self) -> bool {
+        self.held_keys.contains(&Key::LCtrl) || self.held_keys.contains(&Key::RCtrl)
+    }
+
+    // clicking selects a part, shift adds or removes it, clicking nothing starts a box
+    fn click_select(&mut self) {
+        let shift = self.shift_held();
+        match self.get_part_at(self.mouse_position_world.coords) {
+            Some(index) => {
+                let already_selected = self.selected_parts.contains(&index);
+                let selected = if shift && already_selected {
+                    self.selected_parts
+                        .iter()
+                        .cloned()
+                        .filter(|&i| i != index)
+                        .collect()
+                } else if shift {
+                    let mut selected = self.selected_parts.clone();
+                    selected.push(index);
+                    selected
+                } else if already_selected {
+                    // keep the rest of the selection so it can be worked on together
+                    self.selected_parts.clone()
+                } else {
+                    vec![index]
+                };
+                self.select(selected);
+            }
+            None => {
+                if !shift {
+                    self.select(Vec::new());
+                }
+                self.selection_start = Some(self.mouse_position_world);
+            }
+        }
+    }
+
+    // the selection, plus anything attached only to selected shapes so it follows along, a joint
+    // that also holds an unselected shape is handled by `follow_moved_shapes`
+    fn affected_parts(&self) -> Vec<usize> {
+        let mut affected = self
+            .selected_parts
+            .iter()
+            .cloned()
+            .filter(|&index| !self.is_terrain(index))
+            .collect::<Vec<_>>();
+        let selected = affected.clone();
         for (i, part) in self.parts.iter().enumerate() {
-            if part.is_point_inside(point) {
+            let shapes = part.shapes();
+            if !affected.contains(&i)
+                && !shapes.is_empty()
+                && shapes.iter().all(|s| selected.contains(s))
+            {
+                affected.push(i);
+            }
+        }
+        affected
+    }
+
+    // joints between a moved shape and one that stays put keep their anchor on the moved shape
+    fn follow_moved_shapes(
+        &mut self,
+        affected: &[usize],
+        transform: &dyn Fn(Point2<f64>) -> Point2<f64>,
+    ) {
+        for (i, part) in self.parts.iter_mut().enumerate() {
+            if affected.contains(&i) {
+                continue;
+            }
+            if let Some(joint) = part.as_joint_mut() {
+                for shape in joint.shapes() {
+                    if affected.contains(&shape) {
+                        joint.move_anchor(shape, transform);
+                    }
+                }
+            }
+        }
+    }
+
+    fn selection_bounds(&self) -> Option<(Point2<f64>, Point2<f64>)> {
+        util::bounds(
+            self.selected_parts
+                .iter()
+                .map(|&index| self.parts[index].as_ref()),
+        )
+    }
+
+    // grabbing a handle rotates or resizes, grabbing a selected part moves it
+    fn start_drag(&mut self) {
+        let mouse = self.mouse_position_world;
+        let mouse_screen = self.mouse_position;
+        if let Some((min, max)) = self.selection_bounds() {
+            let center = nalgebra::center(&min, &max);
+            let handles = Handles::new(&self.camera, min, max);
+            let near =
+                |handle: &Vector2<f64>| (handle - mouse_screen).norm() <= visualizer::HANDLE_SIZE;
+            if near(&handles.rotation) {
+                let offset = mouse - center;
+                self.dragging = Some(Drag::Rotate {
+                    center,
+                    last_angle: offset.y.atan2(offset.x),
+                });
+                return;
+            }
+            if handles.corners.iter().any(near) {
+                self.dragging = Some(Drag::Resize {
+                    center,
+                    last_distance: nalgebra::distance(&center, &mouse),
+                });
+                return;
+            }
+        }
+        self.click_select();
+        // clicking on a part, instead of starting a box, lets us move it
+        if self.selection_start.is_none() && !self.selected_parts.is_empty() && !self.shift_held() {
+            self.dragging = Some(Drag::Move { last: mouse });
+        }
+    }
+
+    fn drag(&mut self) {
+        let drag = match self.dragging {
+            Some(drag) => drag,
+            None => return,
+        };
+        let affected = self.affected_parts();
+        if affected.is_empty() {
+            return;
+        }
+        if !self.drag_moved {
+            self.drag_moved = true;
+            self.record(match drag {
+                Drag::Move { .. } => "Move",
+                Drag::Rotate { .. } => "Rotate",
+                Drag::Resize { .. } => "Resize",
+            });
+        }
+        let mouse = self.mouse_position_world;
+        self.dragging = Some(match drag {
+            Drag::Move { last } => {
+                let offset = mouse - last;
+                for &index in &affected {
+                    self.parts[index].translate(offset);
+                }
+                self.follow_moved_shapes(&affected, &|point| point + offset);
+                Drag::Move { last: mouse }
+            }
+            Drag::Rotate { center, last_angle } => {
+                let offset = mouse - center;
+                let angle = offset.y.atan2(offset.x);
+                let rotation = UnitComplex::new(angle - last_angle);
+                for &index in &affected {
+                    self.parts[index].rotate_about(center, angle - last_angle);
+                }
+                self.follow_moved_shapes(&affected, &|point| center + rotation * (point - center));
+                Drag::Rotate {
+                    center,
+                    last_angle: angle,
+                }
+            }
+            Drag::Resize {
+                center,
+                last_distance,
+            } => {
+                let distance = nalgebra::distance(&center, &mouse);
+                if last_distance < EPSILON {
+                    return;
+                }
+                let (min, max) = self.scale_limits(&affected);
+                let factor = util::clamp(distance / last_distance, min, max);
+                for &index in &affected {
+                    self.parts[index].scale_about(center, factor);
+                }
+                self.follow_moved_shapes(&affected, &|point| center + (point - center) * factor);
+                Drag::Resize {
+                    center,
+                    last_distance: last_distance * factor,
+                }
+            }
+        });
+    }
+
+    // the material only takes effect the next time the robot starts, the edit is applied to each
+    // shape's own material so fields that weren't changed stay as they are
+    fn set_material<F: Fn(&mut Material)>(&mut self, edit: F) {
+        self.record_merged("Change material");
+        for &index in &self.selected_parts {
+            if self.is_terrain(index) {
+                continue;
+            }
+            if let Some(shape) = self.parts[index].as_shape_mut() {
+                let mut material = shape.material();
+                edit(&mut material);
+                shape.set_material(material);
+            }
+        }
+    }
+
+    // like the material, the layer only takes effect the next time the robot starts
+    fn set_layer(&mut self, layer: usize) {
+        self.record("Change layer");
+        for &index in &self.selected_parts {
+            if self.is_terrain(index) {
+                continue;
+            }
+            if let Some(shape) = self.parts[index].as_shape_mut() {
+                shape.set_layer(layer);
+            }
+        }
+    }
+
+    // every part has to stay inside of its limits, so they can only be scaled so far together
+    fn scale_limits(&self, parts: &[usize]) -> (f64, f64) {
+        parts
+            .iter()
+            .map(|&index| self.parts[index].scale_limits())
+            .fold((0.0, std::f64::INFINITY), |(min, max), (lo, hi)| {
+                (f64::max(min, lo), f64::min(max, hi))
+            })
+    }
+
+    // scales the selection, and everything attached to it, around the middle of the selection
+    fn scale_selected(&mut self, factor: f64) {
+        if self.running || self.playback.is_some() {
+            return;
+        }
+        let center = match self.selection_bounds() {
+            Some((min, max)) => nalgebra::center(&min, &max),
+            None => {
+                self.status = "Nothing selected to scale".to_owned();
+                return;
+            }
+        };
+        let affected = self.affected_parts();
+        if affected.is_empty() {
+            return;
+        }
+        let (min, max) = self.scale_limits(&affected);
+        if min > max {
+            self.status = "Some parts are already outside of the size limits".to_owned();
+            return;
+        }
+        let clamped = util::clamp(factor, min, max);
+        self.record("Scale");
+        for &index in &affected {
+            self.parts[index].scale_about(center, clamped);
+        }
+        self.follow_moved_shapes(&affected, &|point| center + (point - center) * clamped);
+        let bounds = util::bounds(affected.iter().map(|&index| self.parts[index].as_ref()));
+        if let Some((min, max)) = bounds {
+            if !self.can_build_at(min, max) {
+                if let Some((parts, layers)) = self.history.revert() {
+                    self.parts = parts;
+                    self.layers = layers;
+                    let selected = self.selected_parts.clone();
+                    self.select(selected);
+                }
+                return;
+            }
+        }
+        self.status = if (clamped - factor).abs() > EPSILON {
+            format!(
+                "Scaled by {:.2} instead of {:.2} to stay inside of the size limits",
+                clamped, factor
+            )
+        } else {
+            format!("Scaled by {:.2}", clamped)
+        };
+    }
+
+    fn finish_drag(&mut self) {
+        if self.dragging.take().is_none() {
+            return;
+        }
+        if self.drag_moved {
+            self.drag_moved = false;
+            // challenges don't let us drag parts out of the build area
+            let bounds = util::bounds(
+                self.affected_parts()
+                    .into_iter()
+                    .map(|index| self.parts[index].as_ref()),
+            );
+            if let Some((min, max)) = bounds {
+                if !self.can_build_at(min, max) {
+                    if let Some((parts, layers)) = self.history.revert() {
+                        self.parts = parts;
+                        self.layers = layers;
+                        let selected = self.selected_parts.clone();
+                        self.select(selected);
+                    }
+                }
+            }
+        }
+    }
+
+    // flips the selection, and everything attached to it, around the middle of the selection
+    fn mirror_selected(&mut self, mirror: Mirror) {
+        if self.running || self.playback.is_some() {
+            return;
+        }
+        let center = match self.selection_bounds() {
+            Some((min, max)) => nalgebra::center(&min, &max),
+            None => {
+                self.status = "Nothing selected to mirror".to_owned();
+                return;
+            }
+        };
+        let affected = self.affected_parts();
+        if affected.is_empty() {
+            return;
+        }
+        self.record("Mirror");
+        for &index in &affected {
+            self.parts[index].mirror_about(center, mirror);
+        }
+        self.follow_moved_shapes(&affected, &|point| mirror.point(center, point));
+    }
+
+    // selects everything completely inside of the box
+    fn finish_box_select(&mut self) {
+        let start = match self.selection_start.take() {
+            Some(start) => start,
+            None => return,
+        };
+        let min = nalgebra::inf(&start, &self.mouse_position_world);
+        let max = nalgebra::sup(&start, &self.mouse_position_world);
+        let mut selected = if self.shift_held() {
+            self.selected_parts.clone()
+        } else {
+            Vec::new()
+        };
+        for (i, part) in self.parts.iter().enumerate() {
+            let (part_min, part_max) = part.bounds();
+            let inside = part_min.x >= min.x
+                && part_min.y >= min.y
+                && part_max.x <= max.x
+                && part_max.y <= max.y;
+            if inside && !selected.contains(&i) {
+                selected.push(i);
+            }
+        }
+        self.select(selected);
+    }
+
+    fn save_replay(&mut self) {
+        let replay = match &self.last_replay {
+            Some(replay) => replay,
+            None => {
+                self.status = "Nothing to save, run the robot first".to_owned();
+                return;
+            }
+        };
+        match replay.save(&self.file_name) {
+            Ok(()) => {
+                log::info!("Saved replay to {}", self.file_name);
+                self.status = format!("Saved replay {}", self.file_name);
+            }
+            Err(e) => {
+                log::error!("Unable to save replay to {}: {}", self.file_name, e);
+                self.status = format!("Unable to save {}: {}", self.file_name, e);
+            }
+        }
+    }
+
+    fn load_replay(&mut self) {
+        let replay = match Replay::load(&self.file_name) {
+            Ok(replay) => replay,
+            Err(e) => {
+                log::error!("Unable to load replay from {}: {}", self.file_name, e);
+                self.status = format!("Unable to load {}: {}", self.file_name, e);
+                return;
+            }
+        };
+        if self.running {
+            self.stop();
+        }
+        self.cancel_action();
+        self.playback = Some(Playback::new(replay));
+        log::info!("Playing replay from {}", self.file_name);
+        self.status = format!("Playing {}", self.file_name);
+    }
+
+    fn load_challenge(&mut self) {
+        let challenge = match Challenge::load(&self.file_name) {
+            Ok(challenge) => challenge,
+            Err(e) => {
+                log::error!("Unable to load challenge from {}: {}", self.file_name, e);
+                self.status = format!("Unable to load {}: {}", self.file_name, e);
+                return;
+            }
+        };
+        if self.running {
+            self.stop();
+        }
+        self.cancel_action();
+        self.selected_parts.clear();
+        self.playback = None;
+        self.history.clear();
+        self.layers = Layers::default();
+        self.parts = challenge.terrain_parts();
+        log::info!(
+            "Loaded challenge {} from {}",
+            challenge.name(),
+            self.file_name
+        );
+        self.status = format!("Loaded challenge {}", challenge.name());
+        self.challenge = Some(ChallengeState::new(challenge));
+    }
+
+    // challenges only let us build inside of their build area
+    fn can_build_at(&mut self, min: Point2<f64>, max: Point2<f64>) -> bool {
+        if let Some(challenge) = &self.challenge {
+            if !challenge.challenge().build_area().contains_box(min, max) {
+                self.status = "Parts have to be inside the build area".to_owned();
+                return false;
+            }
+        }
+        true
+    }
+
+    // adds a new part, as long as it is somewhere we're allowed to build
+    fn add_part(&mut self, mut part: Box<dyn Part>) -> bool {
+        let (min, max) = part.bounds();
+        if !self.can_build_at(min, max) {
+            return false;
+        }
+        self.record("Create part");
+        // new parts go on top
+        part.set_z_order(self.top_z_order() + 1);
+        self.parts.push(part);
+        true
+    }
+
+    fn top_z_order(&self) -> i32 {
+        self.parts
+            .iter()
+            .map(|part| part.z_order())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // changes where the selection is drawn, `restack` gets every part from the bottom up and
+    // the selection, afterwards the z orders are counted up from the bottom again
+    fn restack(&mut self, name: &'static str, restack: fn(&mut Vec<usize>, &[usize])) {
+        if self.running || self.selected_parts.is_empty() {
+            return;
+        }
+        self.record(name);
+        let mut order = util::draw_order(&self.parts);
+        restack(&mut order, &self.selected_parts);
+        for (z_order, &index) in order.iter().enumerate() {
+            self.parts[index].set_z_order(z_order as i32);
+        }
+    }
+
+    fn move_to_front(&mut self) {
+        self.restack("Move to front", |order, selected| {
+            let (mut moved, rest): (Vec<_>, Vec<_>) =
+                order.drain(..).partition(|index| selected.contains(index));
+            *order = rest;
+            order.append(&mut moved);
+        });
+    }
+
+    fn move_to_back(&mut self) {
+        self.restack("Move to back", |order, selected| {
+            let (moved, mut rest): (Vec<_>, Vec<_>) =
+                order.drain(..).partition(|index| selected.contains(index));
+            *order = moved;
+            order.append(&mut rest);
+        });
+    }
+
+    // moves each selected part above the part right above it
+    fn move_forward(&mut self) {
+        self.restack("Move forward", |order, selected| {
+            // going from the top down lets a run of selected parts move up together
+            for i in (1..order.len()).rev() {
+                if selected.contains(&order[i - 1]) && !selected.contains(&order[i]) {
+                    order.swap(i - 1, i);
+                }
+            }
+        });
+    }
+
+    fn move_backward(&mut self) {
+        self.restack("Move backward", |order, selected| {
+            for i in 1..order.len() {
+                if selected.contains(&order[i]) && !selected.contains(&order[i - 1]) {
+                    order.swap(i - 1, i);
+                }
+            }
+        });
+    }
+
+    // the first click of every joint picks the topmost shape under it
+    fn start_joint(&mut self) {
+        match self.get_shapes_at(self.mouse_position_world.coords).first() {
+            Some(&shape) => {
+                self.action.advance_step();
+                self.action.set_first_click(self.mouse_position);
+                self.action.set_first_click_world(self.mouse_position_world);
+                self.action.set_first_body(Some(shape));
+            }
+            None => {
+                self.status = "Joints have to start on a shape".to_owned();
+                self.action.reset();
+            }
+        }
+    }
+
+    // pins the first shape to the one clicked on, at the first click, they have to overlap there
+    fn add_pinned_joint(&mut self, at: Point2<f64>) {
+        let shape1 = match self.action.first_body() {
+            Some(shape) => shape,
+            None => return,
+        };
+        let anchor = self.action.first_click_world();
+        let shape2 = self
+            .get_shapes_at(at.coords)
+            .into_iter()
+            .find(|&shape| shape != shape1 && self.parts[shape].is_point_inside(anchor.coords));
+        let shape2 = match shape2 {
+            Some(shape) => shape,
+            None => {
+                self.status = "Joints need two shapes overlapping where they start".to_owned();
+                return;
+            }
+        };
+        let mut builder = match self.action.kind() {
+            ActionKind::CreatingFixedJoint => JointBuilder::fixed(shape1, shape2),
+            _ => JointBuilder::revolute(shape1, shape2),
+        };
+        let joint = builder.anchor1(anchor).anchor2(anchor).build();
+        self.add_part(Box::new(joint));
+    }
+
+    // lets the shape under a point slide along the line from where the joint was started
+    fn add_sliding_joint(&mut self, at: Point2<f64>) {
+        let shape1 = match self.action.first_body() {
+            Some(shape) => shape,
+            None => return,
+        };
+        let start = self.action.first_click_world();
+        let shape2 = self
+            .get_shapes_at(at.coords)
+            .into_iter()
+            .find(|&shape| shape != shape1);
+        let shape2 = match shape2 {
+            Some(shape) => shape,
+            None => {
+                self.status = "Sliding joints end on another shape".to_owned();
+                return;
+            }
+        };
+        let axis = at - start;
+        if axis.norm() < EPSILON {
+            self.status = "Sliding joints need a direction".to_owned();
+            return;
+        }
+        let joint = JointBuilder::prismatic(shape1, shape2)
+            .anchor1(start)
+            .anchor2(at)
+            .axis(axis)
+            .build();
+        self.add_part(Box::new(joint));
+    }
+
+    // sticks a thruster on the topmost shape under a point, pushing upwards
+    fn add_thruster(&mut self, at: Point2<f64>) {
+        let shape = match self.get_shapes_at(at.coords).first() {
+            Some(&shape) => shape,
+            None => {
+                self.status = "Thrusters have to be put on a shape".to_owned();
+                return;
+            }
+        };
+        let thruster = Thruster::new(shape, at, -std::f64::consts::FRAC_PI_2);
+        self.add_part(Box::new(thruster));
+    }
+
+    // puts a cannon on the topmost shape under a point, aiming to the right
+    fn add_cannon(&mut self, at: Point2<f64>) {
+        let shape = match self.get_shapes_at(at.coords).first() {
+            Some(&shape) => shape,
+            None => {
+                self.status = "Cannons have to be put on a shape".to_owned();
+                return;
+            }
+        };
+        let cannon = Cannon::new(shape, at, 0.0);
+        self.add_part(Box::new(cannon));
+    }
+
+    // places a corner of a polygon, clicking on the first corner again finishes it
+    fn add_polygon_point(&mut self, at: Point2<f64>) {
+        let at_screen = self.camera.to_global(at.coords);
+        let closing = match self.action.points().first() {
+            Some(first) => {
+                self.action.points().len() >= 3
+                    && (self.camera.to_global(first.coords) - at_screen).norm()
+                        <= POLYGON_CLOSE_DISTANCE
+            }
+            None => false,
+        };
+        if !closing {
+            self.action.add_point(at);
+        }
+        if closing || self.action.points().len() >= limits::MAX_POLYGON_POINTS {
+            self.finish_polygon();
+        } else {
+            self.status = "Click on the first corner or press Enter to finish".to_owned();
+        }
+    }
+
+    // builds a polygon around the placed corners, if they make one
+    fn finish_polygon(&mut self) {
+        let points = self
+            .action
+            .points()
+            .iter()
+            .map(|point| point.coords)
+            .collect::<Vec<_>>();
+        self.action.reset();
+        let mut corners: Vec<Vector2<f64>> = Vec::new();
+        for point in points {
+            if corners
+                .iter()
+                .all(|corner| (corner - point).norm() > EPSILON)
+            {
+                corners.push(point);
+            }
+        }
+        if corners.len() < 3 {
+            self.status = "A polygon needs at least 3 different corners".to_owned();
+            return;
+        }
+        let hull = match convex_hull(&corners) {
+            Some(hull) => hull,
+            None => {
+                self.status = "Those corners don't make a polygon".to_owned();
+                return;
+            }
+        };
+        // keep the points around the center, so it rotates around it
+        let center =
+            hull.iter().fold(Vector2::zeros(), |sum, point| sum + point) / hull.len() as f64;
+        let points = hull.iter().map(|point| point - center).collect::<Vec<_>>();
+        let size = points.iter().map(|point| point.norm()).fold(0.0, f64::max);
+        if size < limits::MIN_POLYGON_SIZE || size > limits::MAX_POLYGON_SIZE {
+            self.status = "That polygon is too small or too big".to_owned();
+            return;
+        }
+        let polygon = ShapeBuilder::polygon(points).position(center).build();
+        if self.add_part(Box::new(polygon)) {
+            self.status.clear();
+        }
+    }
+
+    // puts a label down, it sticks to the topmost shape under it if there is one
+    fn add_text(&mut self, at: Point2<f64>) {
+        let shape = self.get_shapes_at(at.coords).first().cloned();
+        let text = Text::new("Text".to_owned(), at, shape);
+        if self.add_part(Box::new(text)) {
+            self.select(vec![self.parts.len() - 1]);
+        }
+    }
+
+    // makes a label follow the topmost shape under where it starts, or stop following one
+    fn attach_text(&mut self, index: usize, attach: bool) {
+        let position = match self.parts[index].as_text() {
+            Some(text) => text.iso().translation.vector,
+            None => return,
+        };
+        let shape = if attach {
+            match self.get_shapes_at(position).first() {
+                Some(&shape) => Some(shape),
+                None => {
+                    self.status = "Text has to start on a shape to be attached".to_owned();
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+        self.record("Attach text");
+        if let Some(text) = self.parts[index].as_text_mut() {
+            text.set_shape(shape);
+        }
+    }
+
+    // escape clears the key, space is taken by starting and stopping
+    // joints use the slot, thrusters and cannons only have the one key
+    fn bind_key(&mut self, index: usize, slot: KeySlot, key: Key) {
+        let key = match key {
+            Key::Escape => None,
+            Key::Space => {
+                self.status = "Space starts and stops the robot".to_owned();
+                return;
+            }
+            key => Some(key),
+        };
+        if index >= self.parts.len() {
+            return;
+        }
+        self.record("Bind key");
+        let part = &mut self.parts[index];
+        if let Some(joint) = part.as_joint_mut() {
+            let mut motor = joint.motor();
+            match slot {
+                KeySlot::Forward => motor.forward = key,
+                KeySlot::Backward => motor.backward = key,
+            }
+            joint.set_motor(motor);
+        } else if let Some(thruster) = part.as_thruster_mut() {
+            thruster.set_key(key);
+        } else if let Some(cannon) = part.as_cannon_mut() {
+            cannon.set_key(key);
+        }
+    }
+
+    fn is_bound(&self, key: Key) -> bool {
+        self.parts.iter().any(|part| {
+            if let Some(joint) = part.as_joint() {
+                joint.motor().enabled && joint.motor().is_bound_to(key)
+            } else if let Some(thruster) = part.as_thruster() {
+                thruster.key() == Some(key)
+            } else if let Some(cannon) = part.as_cannon() {
+                cannon.key() == Some(key)
+            } else {
+                false
+            }
+        })
+    }
+
+    fn is_terrain(&self, index: usize) -> bool {
+        match &self.challenge {
+            Some(challenge) => challenge.is_terrain(index),
+            None => false,
+        }
+    }
+
+    // remembers the parts as they are, call this right before editing them
+    fn record(&mut self, name: &'static str) {
+        self.history.record(name, &self.parts, &self.layers);
+    }
+
+    // like `record`, for edits to the selection that come in a stream, like dragging a slider
+    fn record_merged(&mut self, name: &'static str) {
+        self.history
+            .record_merged(name, &self.selected_parts, &self.parts, &self.layers);
+    }
+
+    fn undo(&mut self) {
+        if self.running || self.playback.is_some() {
+            return;
+        }
+        match self.history.undo(&self.parts, &self.layers) {
+            Some((parts, layers)) => self.restore(parts, layers),
+            None => self.status = "Nothing to undo".to_owned(),
+        }
+    }
+
+    fn redo(&mut self) {
+        if self.running || self.playback.is_some() {
+            return;
+        }
+        match self.history.redo(&self.parts, &self.layers) {
+            Some((parts, layers)) => self.restore(parts, layers),
+            None => self.status = "Nothing to redo".to_owned(),
+        }
+    }
+
+    fn restore(&mut self, parts: Vec<Box<dyn Part>>, layers: Layers) {
+        self.cancel_action();
+        // the old selection may not exist anymore
+        self.selected_parts.clear();
+        self.parts = parts;
+        self.layers = layers;
+    }
+
+    // removes parts along with anything attached to them, terrain always stays
+    fn remove_parts(&mut self, indices: &[usize]) {
+        let mut removed = vec![false; self.parts.len()];
+        for &index in indices {
+            removed[index] = !self.is_terrain(index);
+        }
+        for (i, part) in self.parts.iter().enumerate() {
+            if part.shapes().iter().any(|&shape| removed[shape]) {
+                removed[i] = true;
+            }
+        }
+        if !removed.contains(&true) {
+            return;
+        }
+        self.record("Delete");
+        // where every part that is left ends up
+        let mut new_indices = Vec::with_capacity(removed.len());
+        let mut next = 0;
+        for &is_removed in &removed {
+            new_indices.push(next);
+            if !is_removed {
+                next += 1;
+            }
+        }
+        let mut i = 0;
+        self.parts.retain(|_| {
+            i += 1;
+            !removed[i - 1]
+        });
+        for part in &mut self.parts {
+            part.remap_shapes(&|shape| new_indices[shape]);
+        }
+        self.selected_parts.clear();
+    }
+
+    fn delete_selected(&mut self) {
+        if self.running {
+            return;
+        }
+        let selected = self.selected_parts.clone();
+        self.remove_parts(&selected);
+    }
+
+    fn copy_selected(&mut self) {
+        if self.selected_parts.is_empty() {
+            self.status = "Nothing selected to copy".to_owned();
+            return;
+        }
+        self.clipboard.copy(&self.parts, &self.selected_parts);
+    }
+
+    fn cut_selected(&mut self) {
+        if self.running {
+            return;
+        }
+        self.copy_selected();
+        self.delete_selected();
+    }
+
+    // pastes right away at `at`, or waits for a click to place it when there is none
+    fn paste(&mut self, at: Option<Point2<f64>>) {
+        if self.clipboard.is_empty() {
+            self.status = "Nothing to paste".to_owned();
+            return;
+        }
+        self.start_action(ActionKind::PlacingParts);
+        if self.action.kind() != ActionKind::PlacingParts {
+            return;
+        }
+        self.placing = self.clipboard.parts();
+        match at {
+            // when it doesn't fit there, it waits for a click instead
+            Some(at) if self.place_parts(at) => self.action.reset(),
+            Some(_) => {}
+            None => self.status = "Click to paste".to_owned(),
+        }
+    }
+
+    // removes everything except for the ground
+    fn clear_all(&mut self) {
+        if self.running {
+            return;
+        }
+        let indices = self
+            .parts
+            .iter()
+            .enumerate()
+            .filter(|(_, part)| !part.as_shape().map_or(false, |shape| shape.ground()))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        self.remove_parts(&indices);
+    }
+
+    // the topmost part at a point in world space
+    fn get_part_at(&self, point: Vector2<f64>) -> Option<usize> {
+        // the last part drawn is on top
+        for i in util::draw_order(&self.parts).into_iter().rev() {
+            if self.parts[i].is_point_inside(point) {
                 log::trace!("part found. index: {}", i);
                 return Some(i);
             }
         }
         None
     }
+
+    // every shape at a point, topmost first
+    fn get_shapes_at(&self, point: Vector2<f64>) -> Vec<usize> {
+        util::draw_order(&self.parts)
+            .into_iter()
+            .rev()
+            .filter(|&i| self.parts[i].as_shape().is_some() && self.parts[i].is_point_inside(point))
+            .collect()
+    }
 }
 
 impl Screen for GameScreen {
-    fn update(&mut self, _dt: f64) {
+    fn update(&mut self, dt: f64) {
+        self.pan(dt);
+        // the camera may have moved without the mouse moving
+        self.mouse_position_world = Point2::from(self.camera.to_local(self.mouse_position));
+
+        if let Some(playback) = &mut self.playback {
+            playback.update();
+            return;
+        }
+
+        if self.running {
+            for part in &mut self.parts {
+                part.drive(&mut self.world, &self.held_keys);
+            }
+        }
+
         self.world.step();
 
         for part in &mut self.parts {
             part.update(&self.world);
         }
+
+        if let Some(recording) = &mut self.recording {
+            recording.record(&self.parts, self.held_keys.iter().cloned().collect());
+        }
+
+        if self.running {
+            if let Some(challenge) = &mut self.challenge {
+                challenge.update(self.world.timestep(), &self.parts);
+            }
+            self.follow_focus(dt);
+        }
     }
 
     fn update_gui(&mut self, ui: &mut UiCell, ids: &Ids) {
@@ -156,6 +1409,16 @@ impl Screen for GameScreen {
         const UTILITY_BUTTON_COLOR: Color = color::LIGHT_ORANGE;
         const BUTTON_MARGIN: Scalar = 5.0;
 
+        self.mouse_over_gui = match ui.global_input().current.widget_under_mouse {
+            Some(widget) => widget != ui.window,
+            None => false,
+        };
+        self.typing = ui
+            .global_input()
+            .current
+            .widget_capturing_keyboard
+            .is_some();
+
         widget::Canvas::new()
             .color(color::PURPLE)
             .h(80.0)
@@ -197,22 +1460,30 @@ impl Screen for GameScreen {
         {
             self.start_action(ActionKind::CreatingTriangle);
         }
-        widget::Button::new()
+        if widget::Button::new()
             .color(UTILITY_BUTTON_COLOR)
             .label_font_size(12)
             .label("Undo")
             .parent(ids.canvas)
             .right_from(ids.triangle_button, BUTTON_MARGIN)
             .wh([60.0, 20.0])
-            .set(ids.undo_button, ui);
-        widget::Button::new()
+            .set(ids.undo_button, ui)
+            .was_clicked()
+        {
+            self.undo();
+        }
+        if widget::Button::new()
             .color(UTILITY_BUTTON_COLOR)
             .label_font_size(12)
             .label("Redo")
             .parent(ids.canvas)
             .right_from(ids.undo_button, BUTTON_MARGIN)
             .wh([60.0, 20.0])
-            .set(ids.redo_button, ui);
+            .set(ids.redo_button, ui)
+            .was_clicked()
+        {
+            self.redo();
+        }
         if widget::Button::new()
             .color(color::LIGHT_RED)
             .label_font_size(12)
@@ -223,25 +1494,33 @@ impl Screen for GameScreen {
             .set(ids.zoom_in_button, ui)
             .was_clicked()
         {
-            self.zoom_in();
+            self.zoom_in(self.screen_center());
         }
 
-        widget::Button::new()
+        if widget::Button::new()
             .color(MAIN_BUTTON_COLOR)
             .down_from(ids.circle_button, BUTTON_MARGIN)
             .label_font_size(12)
             .label("Fixed Joint")
             .parent(ids.canvas)
             .wh([80.0, 20.0])
-            .set(ids.fixed_joint_button, ui);
-        widget::Button::new()
+            .set(ids.fixed_joint_button, ui)
+            .was_clicked()
+        {
+            self.start_action(ActionKind::CreatingFixedJoint);
+        }
+        if widget::Button::new()
             .color(MAIN_BUTTON_COLOR)
             .label_font_size(12)
             .label("Rotating Joint")
             .parent(ids.canvas)
             .right_from(ids.fixed_joint_button, BUTTON_MARGIN)
             .wh([80.0, 20.0])
-            .set(ids.rotating_joint_button, ui);
+            .set(ids.rotating_joint_button, ui)
+            .was_clicked()
+        {
+            self.start_action(ActionKind::CreatingRotatingJoint);
+        }
         if widget::Button::new()
             .color(MAIN_BUTTON_COLOR)
             .label_font_size(12)
@@ -254,14 +1533,18 @@ impl Screen for GameScreen {
         {
             self.start_action(ActionKind::CreatingSlidingJoint);
         }
-        widget::Button::new()
+        if widget::Button::new()
             .color(MAIN_BUTTON_COLOR)
             .label_font_size(12)
             .label("Text")
             .parent(ids.canvas)
             .right_from(ids.sliding_joint_button, BUTTON_MARGIN)
             .wh([60.0, 20.0])
-            .set(ids.text_button, ui);
+            .set(ids.text_button, ui)
+            .was_clicked()
+        {
+            self.start_action(ActionKind::CreatingText);
+        }
         if widget::Button::new()
             .color(UTILITY_BUTTON_COLOR)
             .label_font_size(12)
@@ -272,7 +1555,7 @@ impl Screen for GameScreen {
             .set(ids.paste_button, ui)
             .was_clicked()
         {
-            // let _ = self.sender.send(GuiEvent::PasteClicked);
+            self.paste(None);
         }
         if widget::Button::new()
             .color(color::LIGHT_RED)
@@ -284,7 +1567,7 @@ impl Screen for GameScreen {
             .set(ids.zoom_out_button, ui)
             .was_clicked()
         {
-            self.zoom_out();
+            self.zoom_out(self.screen_center());
         }
 
         if self.running {
@@ -319,6 +1602,7 @@ impl Screen for GameScreen {
             &[
                 "Main menu",
                 "Save...",
+                "Save replay",
                 "Load robot",
                 "Load and insert",
                 "Load replay",
@@ -333,18 +1617,23 @@ impl Screen for GameScreen {
         .wh([100.0, 20.0])
         .set(ids.file, ui)
         {
-            // let ev = match index {
-            //     0 => Some(GuiEvent::FileMainMenuClicked),
-            //     1 => Some(GuiEvent::FileSaveClicked),
-            //     2 => Some(GuiEvent::FileLoadRobotClicked),
-            //     3 => Some(GuiEvent::FileLoadAndInsertClicked),
-            //     4 => Some(GuiEvent::FileLoadReplayClicked),
-            //     5 => Some(GuiEvent::FileLoadChallengeClicked),
-            //     _ => None,
-            // };
-            // if let Some(ev) = ev {
-            //     let _ = self.sender.send(ev);
-            // }
+            match index {
+                // Main menu
+                0 => {}
+                // Save...
+                1 => self.save_robot(),
+                // Save replay
+                2 => self.save_replay(),
+                // Load robot
+                3 => self.load_robot(),
+                // Load and insert
+                4 => self.load_and_insert_robot(),
+                // Load replay
+                5 => self.load_replay(),
+                // Load challenge
+                6 => self.load_challenge(),
+                _ => {}
+            }
         }
 
         if let Some(index) = widget::DropDownList::new(
@@ -359,6 +1648,8 @@ impl Screen for GameScreen {
                 "Delete",
                 "Move to front",
                 "Move to back",
+                "Move forward",
+                "Move backward",
             ],
             None,
         )
@@ -369,33 +1660,55 @@ impl Screen for GameScreen {
         .wh([100.0, 20.0])
         .set(ids.edit, ui)
         {
-            // let ev = match index {
-            //     0 => Some(GuiEvent::EditChangeSettingsClicked),
-            //     1 => Some(GuiEvent::EditClearAllClicked),
-            //     2 => Some(GuiEvent::EditUndoClicked),
-            //     3 => Some(GuiEvent::EditRedoClicked),
-            //     4 => Some(GuiEvent::EditCutClicked),
-            //     5 => Some(GuiEvent::EditCopyClicked),
-            //     6 => Some(GuiEvent::EditPasteClicked),
-            //     7 => Some(GuiEvent::EditDeleteClicked),
-            //     8 => Some(GuiEvent::EditMoveToFrontClicked),
-            //     9 => Some(GuiEvent::EditMoveToBackClicked),
-            //     _ => None,
-            // };
-            // if let Some(ev) = ev {
-            //     let _ = self.sender.send(ev);
-            // }
+            match index {
+                // Change settings
+                0 => self.show_settings = !self.show_settings,
+                // Clear all
+                1 => self.clear_all(),
+                // Undo
+                2 => self.undo(),
+                // Redo
+                3 => self.redo(),
+                // Cut
+                4 => self.cut_selected(),
+                // Copy
+                5 => self.copy_selected(),
+                // Paste
+                6 => self.paste(None),
+                // Delete
+                7 => self.delete_selected(),
+                // Move to front
+                8 => self.move_to_front(),
+                // Move to back
+                9 => self.move_to_back(),
+                // Move forward
+                10 => self.move_forward(),
+                // Move backward
+                11 => self.move_backward(),
+                _ => {}
+            }
         }
 
+        let shown = |shown: bool, name: &str| {
+            if shown {
+                format!("Hide {}", name)
+            } else {
+                format!("Show {}", name)
+            }
+        };
         if let Some(index) = widget::DropDownList::new(
             &[
-                "Zoom in",
-                "Zoom out",
-                "Snap to center",
-                "Show joints",
-                "Show colors",
-                "Show outlines",
-                "Center on selection",
+                "Zoom in".to_owned(),
+                "Zoom out".to_owned(),
+                "Snap to center".to_owned(),
+                shown(self.visualizer.show_joints(), "joints"),
+                shown(self.visualizer.show_colors(), "colors"),
+                if self.visualizer.outlines_only() {
+                    "Show filled".to_owned()
+                } else {
+                    "Show outlines".to_owned()
+                },
+                "Center on selection".to_owned(),
             ],
             None,
         )
@@ -408,19 +1721,32 @@ impl Screen for GameScreen {
         {
             match index {
                 // Zoom in
-                0 => {}
+                0 => self.zoom_in(self.screen_center()),
                 // Zoom out
-                1 => {}
+                1 => self.zoom_out(self.screen_center()),
                 // Snap to center
-                2 => {}
+                2 => self.camera.center_on(Point2::origin()),
                 // Show joints
-                3 => {}
+                3 => {
+                    let show_joints = self.visualizer.show_joints();
+                    self.visualizer.set_show_joints(!show_joints);
+                }
                 // Show colors
-                4 => {}
+                4 => {
+                    let show_colors = self.visualizer.show_colors();
+                    self.visualizer.set_show_colors(!show_colors);
+                }
                 // Show outlines
-                5 => {}
+                5 => {
+                    let outlines_only = self.visualizer.outlines_only();
+                    self.visualizer.set_outlines_only(!outlines_only);
+                }
                 // Center on selection
-                6 => {}
+                6 => {
+                    if let Some((min, max)) = self.selection_bounds() {
+                        self.camera.center_on(nalgebra::center(&min, &max));
+                    }
+                }
                 _ => {}
             }
         }
@@ -432,6 +1758,8 @@ impl Screen for GameScreen {
                 "Scale",
                 "Thrusters",
                 "Cannon",
+                "Collision layers",
+                "Polygon",
             ],
             None,
         )
@@ -442,16 +1770,16 @@ impl Screen for GameScreen {
         .wh([100.0, 20.0])
         .set(ids.extras, ui)
         {
-            // let ev = match index {
-            //     0 => Some(GuiEvent::ExtrasMirrorHorizontalClicked),
-            //     1 => Some(GuiEvent::ExtrasMirrorVerticalClicked),
-            //     2 => Some(GuiEvent::ExtrasScaleClicked),
-            //     3 => Some(GuiEvent::ExtrasCannonClicked),
-            //     _ => None,
-            // };
-            // if let Some(ev) = ev {
-            //     let _ = self.sender.send(ev);
-            // }
+            match index {
+                0 => self.mirror_selected(Mirror::Horizontal),
+                1 => self.mirror_selected(Mirror::Vertical),
+                2 => self.show_scale = !self.show_scale,
+                3 => self.start_action(ActionKind::CreatingThruster),
+                4 => self.start_action(ActionKind::CreatingCannon),
+                5 => self.show_layers = !self.show_layers,
+                6 => self.start_action(ActionKind::CreatingPolygon),
+                _ => {}
+            }
         }
 
         widget::Text::new(&format!("Part count: {}", self.parts.len()))
@@ -462,6 +1790,58 @@ impl Screen for GameScreen {
             .wh([100.0, 20.0])
             .set(ids.part_count_text, ui);
 
+        for event in widget::TextBox::new(&self.file_name)
+            .font_size(12)
+            .parent(ids.canvas)
+            .right_from(ids.part_count_text, BUTTON_MARGIN)
+            .wh([150.0, 20.0])
+            .set(ids.file_name_text_box, ui)
+        {
+            if let widget::text_box::Event::Update(file_name) = event {
+                self.file_name = file_name;
+            }
+        }
+
+        widget::Text::new(&self.status)
+            .color(color::WHITE)
+            .font_size(12)
+            .parent(ids.canvas)
+            .right_from(ids.file_name_text_box, BUTTON_MARGIN)
+            .wh([300.0, 20.0])
+            .set(ids.status_text, ui);
+
+        if let Some(challenge) = &self.challenge {
+            let (text, text_color) = match challenge.outcome() {
+                Outcome::InProgress => (
+                    format!(
+                        "{}: {:.1}s",
+                        challenge.challenge().name(),
+                        challenge.elapsed()
+                    ),
+                    color::WHITE,
+                ),
+                Outcome::Success => (
+                    format!(
+                        "{}: success in {:.1}s!",
+                        challenge.challenge().name(),
+                        challenge.elapsed()
+                    ),
+                    color::LIGHT_GREEN,
+                ),
+                Outcome::Failure => (
+                    format!("{}: failed", challenge.challenge().name()),
+                    color::LIGHT_RED,
+                ),
+            };
+            widget::Text::new(&text)
+                .color(text_color)
+                .font_size(12)
+                .parent(ids.canvas)
+                .down_from(ids.status_text, BUTTON_MARGIN)
+                .wh([300.0, 20.0])
+                .set(ids.challenge_text, ui);
+        }
+
         widget::Canvas::new()
             .color(color::PURPLE)
             .wh([100.0, 640.0 - BUTTON_MARGIN * 2.0])
@@ -469,77 +1849,893 @@ impl Screen for GameScreen {
             .down_from(ids.canvas, BUTTON_MARGIN)
             .set(ids.part_canvas, ui);
 
-        widget::Text::new("Replace me")
+        let part_name = match self.selected_parts.as_slice() {
+            [] => "Nothing selected".to_owned(),
+            [index] => self.parts[*index].name().to_owned(),
+            selected => format!("{} parts", selected.len()),
+        };
+        widget::Text::new(&part_name)
             .font_size(12)
             .parent(ids.part_canvas)
             .wh([80.0, 20.0])
             .mid_top_of(ids.part_canvas)
             .set(ids.part_name_label, ui);
-        widget::Button::new()
+        if widget::Button::new()
             .color(color::LIGHT_ORANGE)
             .label_font_size(12)
             .label("Delete")
             .parent(ids.part_canvas)
             .down_from(ids.part_name_label, BUTTON_MARGIN)
             .wh([80.0, 20.0])
-            .set(ids.part_delete_button, ui);
-        widget::Button::new()
+            .set(ids.part_delete_button, ui)
+            .was_clicked()
+        {
+            self.delete_selected();
+        }
+        if widget::Button::new()
             .color(color::LIGHT_ORANGE)
             .label_font_size(12)
             .label("Cut")
             .parent(ids.part_canvas)
             .down_from(ids.part_delete_button, BUTTON_MARGIN)
             .wh([80.0, 20.0])
-            .set(ids.part_cut_button, ui);
-        widget::Button::new()
+            .set(ids.part_cut_button, ui)
+            .was_clicked()
+        {
+            self.cut_selected();
+        }
+        if widget::Button::new()
             .color(color::LIGHT_ORANGE)
             .label_font_size(12)
             .label("Copy")
             .parent(ids.part_canvas)
             .down_from(ids.part_cut_button, BUTTON_MARGIN)
             .wh([80.0, 20.0])
-            .set(ids.part_copy_button, ui);
-        widget::Button::new()
+            .set(ids.part_copy_button, ui)
+            .was_clicked()
+        {
+            self.copy_selected();
+        }
+        if widget::Button::new()
             .color(color::LIGHT_BLUE)
             .label_font_size(12)
             .label("Paste")
             .parent(ids.part_canvas)
             .down_from(ids.part_copy_button, BUTTON_MARGIN)
             .wh([80.0, 20.0])
-            .set(ids.part_paste_button, ui);
-        widget::Slider::new(15.0, 1.0, 30.0)
+            .set(ids.part_paste_button, ui)
+            .was_clicked()
+        {
+            self.paste(None);
+        }
+        // the material of the first selected shape is shown, changes go to every selected shape
+        let material = self
+            .selected_parts
+            .iter()
+            .filter_map(|&index| self.parts[index].as_shape())
+            .map(|shape| shape.material())
+            .next();
+        let mut edited = material.unwrap_or_default();
+        if let Some(density) =
+            widget::Slider::new(edited.density, limits::MIN_DENSITY, limits::MAX_DENSITY)
+                .label_font_size(12)
+                .label_color(color::DARK_RED)
+                .label("Density")
+                .parent(ids.part_canvas)
+                .down_from(ids.part_paste_button, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_density_slider, ui)
+        {
+            edited.density = density;
+        }
+        if let Some(friction) = widget::Slider::new(edited.friction, 0.0, limits::MAX_FRICTION)
             .label_font_size(12)
             .label_color(color::DARK_RED)
-            .label("Density")
+            .label("Friction")
             .parent(ids.part_canvas)
-            .down_from(ids.part_paste_button, BUTTON_MARGIN)
+            .down_from(ids.part_density_slider, BUTTON_MARGIN)
             .wh([80.0, 20.0])
-            .set(ids.part_density_slider, ui);
-        widget::Toggle::new(true)
+            .set(ids.part_friction_slider, ui)
+        {
+            edited.friction = friction;
+        }
+        if let Some(restitution) =
+            widget::Slider::new(edited.restitution, 0.0, limits::MAX_RESTITUTION)
+                .label_font_size(12)
+                .label_color(color::DARK_RED)
+                .label("Bounce")
+                .parent(ids.part_canvas)
+                .down_from(ids.part_friction_slider, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_restitution_slider, ui)
+        {
+            edited.restitution = restitution;
+        }
+        if let Some(collides) = widget::Toggle::new(edited.collides)
             .parent(ids.part_canvas)
-            .down_from(ids.part_density_slider, BUTTON_MARGIN)
+            .down_from(ids.part_restitution_slider, BUTTON_MARGIN)
             .wh([20.0, 20.0])
-            .set(ids.part_collides_toggle, ui);
+            .set(ids.part_collides_toggle, ui)
+            .last()
+        {
+            edited.collides = collides;
+        }
         widget::Text::new("Collides")
             .font_size(12)
             .right_from(ids.part_collides_toggle, BUTTON_MARGIN)
             .wh([60.0, 20.0])
             .set(ids.part_collides_text, ui);
-        widget::Toggle::new(true)
+        if let Some(material) = material {
+            if material != edited && !self.running {
+                self.set_material(|shape_material| {
+                    if edited.density != material.density {
+                        shape_material.density = edited.density;
+                    }
+                    if edited.friction != material.friction {
+                        shape_material.friction = edited.friction;
+                    }
+                    if edited.restitution != material.restitution {
+                        shape_material.restitution = edited.restitution;
+                    }
+                    if edited.collides != material.collides {
+                        shape_material.collides = edited.collides;
+                    }
+                });
+            }
+        }
+        let layer = self
+            .selected_parts
+            .iter()
+            .filter_map(|&index| self.parts[index].as_shape())
+            .map(|shape| shape.layer())
+            .next();
+        if let Some(layer) = widget::DropDownList::new(self.layers.names(), layer)
+            .label("Layer")
+            .label_font_size(12)
             .parent(ids.part_canvas)
             .down_from(ids.part_collides_toggle, BUTTON_MARGIN)
+            .wh([80.0, 20.0])
+            .set(ids.part_layer_list, ui)
+        {
+            if !self.running {
+                self.set_layer(layer);
+            }
+        }
+        let camera_focus = self
+            .selected_parts
+            .iter()
+            .filter_map(|&index| self.parts[index].as_shape())
+            .map(|shape| shape.camera_focus())
+            .next();
+        if let Some(camera_focus) = widget::Toggle::new(camera_focus.unwrap_or(false))
+            .parent(ids.part_canvas)
+            .down_from(ids.part_layer_list, BUTTON_MARGIN)
             .wh([20.0, 20.0])
-            .set(ids.part_camera_focus_toggle, ui);
+            .set(ids.part_camera_focus_toggle, ui)
+            .last()
+        {
+            if !self.running {
+                self.set_camera_focus(camera_focus);
+            }
+        }
         widget::Text::new("Camera focus")
             .font_size(12)
             .right_from(ids.part_camera_focus_toggle, BUTTON_MARGIN)
             .wh([60.0, 20.0])
             .set(ids.part_camera_focus_text, ui);
+
+        let collide_connected = match self.selected_parts.as_slice() {
+            [index] => self.parts[*index]
+                .as_joint()
+                .map(|joint| (*index, joint.collide_connected())),
+            _ => None,
+        };
+        if let Some((index, collide_connected)) = collide_connected {
+            if let Some(collide_connected) = widget::Toggle::new(collide_connected)
+                .parent(ids.part_canvas)
+                .down_from(ids.part_camera_focus_toggle, BUTTON_MARGIN)
+                .wh([20.0, 20.0])
+                .set(ids.part_joint_collide_toggle, ui)
+                .last()
+            {
+                if !self.running {
+                    self.record("Change joint");
+                    if let Some(joint) = self.parts[index].as_joint_mut() {
+                        joint.set_collide_connected(collide_connected);
+                    }
+                }
+            }
+            widget::Text::new("Collide")
+                .font_size(12)
+                .right_from(ids.part_joint_collide_toggle, BUTTON_MARGIN)
+                .wh([60.0, 20.0])
+                .set(ids.part_joint_collide_text, ui);
+        }
+
+        let joint = match self.selected_parts.as_slice() {
+            [index] => self.parts[*index]
+                .as_joint()
+                .filter(|joint| joint.kind() != JointKind::Fixed)
+                .map(|joint| (*index, joint.kind(), joint.motor(), joint.travel())),
+            _ => None,
+        };
+        if let Some((index, kind, mut motor, mut travel)) = joint {
+            let (max_speed, speed_label) = match kind {
+                JointKind::Prismatic => (limits::MAX_PISTON_SPEED, "Speed (m/s)"),
+                _ => (limits::MAX_MOTOR_SPEED, "Speed (rad/s)"),
+            };
+            let mut changed = false;
+            if let Some(enabled) = widget::Toggle::new(motor.enabled)
+                .parent(ids.part_canvas)
+                .down_from(ids.part_joint_collide_toggle, BUTTON_MARGIN)
+                .wh([20.0, 20.0])
+                .set(ids.part_motor_toggle, ui)
+                .last()
+            {
+                motor.enabled = enabled;
+                changed = true;
+            }
+            widget::Text::new("Motor")
+                .font_size(12)
+                .right_from(ids.part_motor_toggle, BUTTON_MARGIN)
+                .wh([60.0, 20.0])
+                .set(ids.part_motor_text, ui);
+            if let Some(speed) = widget::Slider::new(motor.speed, -max_speed, max_speed)
+                .label_font_size(12)
+                .label_color(color::DARK_RED)
+                .label(speed_label)
+                .parent(ids.part_canvas)
+                .down_from(ids.part_motor_toggle, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_motor_speed_slider, ui)
+            {
+                motor.speed = speed;
+                changed = true;
+            }
+            if let Some(max_force) =
+                widget::Slider::new(motor.max_force, 0.0, limits::MAX_MOTOR_FORCE)
+                    .label_font_size(12)
+                    .label_color(color::DARK_RED)
+                    .label("Strength")
+                    .parent(ids.part_canvas)
+                    .down_from(ids.part_motor_speed_slider, BUTTON_MARGIN)
+                    .wh([80.0, 20.0])
+                    .set(ids.part_motor_force_slider, ui)
+            {
+                motor.max_force = max_force;
+                changed = true;
+            }
+            if kind == JointKind::Prismatic {
+                if let Some(enabled) = widget::Toggle::new(travel.enabled)
+                    .parent(ids.part_canvas)
+                    .down_from(ids.part_motor_force_slider, BUTTON_MARGIN)
+                    .wh([20.0, 20.0])
+                    .set(ids.part_limits_toggle, ui)
+                    .last()
+                {
+                    travel.enabled = enabled;
+                    changed = true;
+                }
+                widget::Text::new("Limits")
+                    .font_size(12)
+                    .right_from(ids.part_limits_toggle, BUTTON_MARGIN)
+                    .wh([60.0, 20.0])
+                    .set(ids.part_limits_text, ui);
+                if let Some(lower) = widget::Slider::new(travel.lower, -limits::MAX_TRAVEL, 0.0)
+                    .label_font_size(12)
+                    .label_color(color::DARK_RED)
+                    .label("Retract")
+                    .parent(ids.part_canvas)
+                    .down_from(ids.part_limits_toggle, BUTTON_MARGIN)
+                    .wh([80.0, 20.0])
+                    .set(ids.part_lower_limit_slider, ui)
+                {
+                    travel.lower = lower;
+                    changed = true;
+                }
+                if let Some(upper) = widget::Slider::new(travel.upper, 0.0, limits::MAX_TRAVEL)
+                    .label_font_size(12)
+                    .label_color(color::DARK_RED)
+                    .label("Extend")
+                    .parent(ids.part_canvas)
+                    .down_from(ids.part_lower_limit_slider, BUTTON_MARGIN)
+                    .wh([80.0, 20.0])
+                    .set(ids.part_upper_limit_slider, ui)
+                {
+                    travel.upper = upper;
+                    changed = true;
+                }
+            }
+            let (forward_label, backward_label) = match kind {
+                JointKind::Prismatic => ("Extend", "Retract"),
+                _ => ("Forward", "Backward"),
+            };
+            let key_label = |slot: KeySlot, name: &str, key: Option<Key>| {
+                if self.binding == Some((index, slot)) {
+                    "Press a key".to_owned()
+                } else {
+                    match key {
+                        Some(key) => format!("{}: {:?}", name, key),
+                        None => format!("{}: none", name),
+                    }
+                }
+            };
+            let forward_label = key_label(KeySlot::Forward, forward_label, motor.forward);
+            let backward_label = key_label(KeySlot::Backward, backward_label, motor.backward);
+            let above = match kind {
+                JointKind::Prismatic => ids.part_upper_limit_slider,
+                _ => ids.part_motor_force_slider,
+            };
+            if widget::Button::new()
+                .color(color::LIGHT_BLUE)
+                .label_font_size(10)
+                .label(&forward_label)
+                .parent(ids.part_canvas)
+                .down_from(above, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_forward_key_button, ui)
+                .was_clicked()
+            {
+                self.binding = Some((index, KeySlot::Forward));
+            }
+            if widget::Button::new()
+                .color(color::LIGHT_BLUE)
+                .label_font_size(10)
+                .label(&backward_label)
+                .parent(ids.part_canvas)
+                .down_from(ids.part_forward_key_button, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_backward_key_button, ui)
+                .was_clicked()
+            {
+                self.binding = Some((index, KeySlot::Backward));
+            }
+            if widget::Button::new()
+                .color(color::LIGHT_BLUE)
+                .label_font_size(10)
+                .label(match motor.mode {
+                    ControlMode::Hold => "Mode: hold",
+                    ControlMode::Toggle => "Mode: toggle",
+                })
+                .parent(ids.part_canvas)
+                .down_from(ids.part_backward_key_button, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_control_mode_button, ui)
+                .was_clicked()
+            {
+                motor.mode = match motor.mode {
+                    ControlMode::Hold => ControlMode::Toggle,
+                    ControlMode::Toggle => ControlMode::Hold,
+                };
+                changed = true;
+            }
+            // joints are only edited while building, so the edit can be undone
+            if changed && !self.running {
+                self.record_merged("Change joint");
+                if let Some(joint) = self.parts[index].as_joint_mut() {
+                    joint.set_motor(motor);
+                    joint.set_travel(travel);
+                }
+            }
+        }
+
+        let thruster = match self.selected_parts.as_slice() {
+            [index] => self.parts[*index]
+                .as_thruster()
+                .map(|thruster| (*index, thruster.strength(), thruster.key())),
+            _ => None,
+        };
+        if let Some((index, strength, key)) = thruster {
+            if let Some(strength) =
+                widget::Slider::new(strength, 0.0, limits::MAX_THRUSTER_STRENGTH)
+                    .label_font_size(12)
+                    .label_color(color::DARK_RED)
+                    .label("Strength")
+                    .parent(ids.part_canvas)
+                    .down_from(ids.part_camera_focus_toggle, BUTTON_MARGIN)
+                    .wh([80.0, 20.0])
+                    .set(ids.part_thruster_strength_slider, ui)
+            {
+                if !self.running {
+                    self.record_merged("Change thruster");
+                    if let Some(thruster) = self.parts[index].as_thruster_mut() {
+                        thruster.set_strength(strength);
+                    }
+                }
+            }
+            let label = if self.binding == Some((index, KeySlot::Forward)) {
+                "Press a key".to_owned()
+            } else {
+                match key {
+                    Some(key) => format!("Fire: {:?}", key),
+                    None => "Fire: always".to_owned(),
+                }
+            };
+            if widget::Button::new()
+                .color(color::LIGHT_BLUE)
+                .label_font_size(10)
+                .label(&label)
+                .parent(ids.part_canvas)
+                .down_from(ids.part_thruster_strength_slider, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_thruster_key_button, ui)
+                .was_clicked()
+            {
+                self.binding = Some((index, KeySlot::Forward));
+            }
+        }
+
+        let cannon = match self.selected_parts.as_slice() {
+            [index] => self.parts[*index].as_cannon().map(|cannon| {
+                (
+                    *index,
+                    [
+                        cannon.muzzle_speed(),
+                        cannon.projectile_radius(),
+                        cannon.projectile_density(),
+                        cannon.cooldown(),
+                    ],
+                    cannon.key(),
+                )
+            }),
+            _ => None,
+        };
+        if let Some((index, mut settings, key)) = cannon {
+            let mut changed = false;
+            if let Some(speed) = widget::Slider::new(settings[0], 0.0, limits::MAX_MUZZLE_SPEED)
+                .label_font_size(12)
+                .label_color(color::DARK_RED)
+                .label("Speed")
+                .parent(ids.part_canvas)
+                .down_from(ids.part_camera_focus_toggle, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_cannon_speed_slider, ui)
+            {
+                settings[0] = speed;
+                changed = true;
+            }
+            if let Some(radius) = widget::Slider::new(
+                settings[1],
+                limits::MIN_PROJECTILE_SIZE,
+                limits::MAX_PROJECTILE_SIZE,
+            )
+            .label_font_size(12)
+            .label_color(color::DARK_RED)
+            .label("Size")
+            .parent(ids.part_canvas)
+            .down_from(ids.part_cannon_speed_slider, BUTTON_MARGIN)
+            .wh([80.0, 20.0])
+            .set(ids.part_cannon_size_slider, ui)
+            {
+                settings[1] = radius;
+                changed = true;
+            }
+            if let Some(density) =
+                widget::Slider::new(settings[2], 0.1, limits::MAX_PROJECTILE_DENSITY)
+                    .label_font_size(12)
+                    .label_color(color::DARK_RED)
+                    .label("Density")
+                    .parent(ids.part_canvas)
+                    .down_from(ids.part_cannon_size_slider, BUTTON_MARGIN)
+                    .wh([80.0, 20.0])
+                    .set(ids.part_cannon_density_slider, ui)
+            {
+                settings[2] = density;
+                changed = true;
+            }
+            if let Some(cooldown) = widget::Slider::new(settings[3], 0.0, limits::MAX_COOLDOWN)
+                .label_font_size(12)
+                .label_color(color::DARK_RED)
+                .label("Cooldown")
+                .parent(ids.part_canvas)
+                .down_from(ids.part_cannon_density_slider, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_cannon_cooldown_slider, ui)
+            {
+                settings[3] = cooldown;
+                changed = true;
+            }
+            let label = if self.binding == Some((index, KeySlot::Forward)) {
+                "Press a key".to_owned()
+            } else {
+                match key {
+                    Some(key) => format!("Fire: {:?}", key),
+                    None => "Fire: none".to_owned(),
+                }
+            };
+            if widget::Button::new()
+                .color(color::LIGHT_BLUE)
+                .label_font_size(10)
+                .label(&label)
+                .parent(ids.part_canvas)
+                .down_from(ids.part_cannon_cooldown_slider, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_cannon_key_button, ui)
+                .was_clicked()
+            {
+                self.binding = Some((index, KeySlot::Forward));
+            }
+            if changed && !self.running {
+                self.record_merged("Change cannon");
+                if let Some(cannon) = self.parts[index].as_cannon_mut() {
+                    cannon.set_muzzle_speed(settings[0]);
+                    cannon.set_projectile_radius(settings[1]);
+                    cannon.set_projectile_density(settings[2]);
+                    cannon.set_cooldown(settings[3]);
+                }
+            }
+        }
+
+        let text = match self.selected_parts.as_slice() {
+            [index] => self.parts[*index].as_text().map(|text| {
+                (
+                    *index,
+                    text.content().to_owned(),
+                    text.size(),
+                    text.color(),
+                    text.shape().is_some(),
+                )
+            }),
+            _ => None,
+        };
+        if let Some((index, content, size, text_color, attached)) = text {
+            for event in widget::TextBox::new(&content)
+                .font_size(12)
+                .parent(ids.part_canvas)
+                .down_from(ids.part_camera_focus_toggle, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_text_content_box, ui)
+            {
+                if let widget::text_box::Event::Update(content) = event {
+                    if !self.running {
+                        self.record_merged("Edit text");
+                        if let Some(text) = self.parts[index].as_text_mut() {
+                            text.set_content(content);
+                        }
+                    }
+                }
+            }
+            if let Some(size) =
+                widget::Slider::new(size, limits::MIN_TEXT_SIZE, limits::MAX_TEXT_SIZE)
+                    .label_font_size(12)
+                    .label_color(color::DARK_RED)
+                    .label("Size")
+                    .parent(ids.part_canvas)
+                    .down_from(ids.part_text_content_box, BUTTON_MARGIN)
+                    .wh([80.0, 20.0])
+                    .set(ids.part_text_size_slider, ui)
+            {
+                if !self.running {
+                    self.record_merged("Change text");
+                    if let Some(text) = self.parts[index].as_text_mut() {
+                        text.set_size(size);
+                    }
+                }
+            }
+            if widget::Button::new()
+                .color(Color::Rgba(
+                    text_color[0],
+                    text_color[1],
+                    text_color[2],
+                    text_color[3],
+                ))
+                .label_font_size(12)
+                .label("Color")
+                .parent(ids.part_canvas)
+                .down_from(ids.part_text_size_slider, BUTTON_MARGIN)
+                .wh([80.0, 20.0])
+                .set(ids.part_text_color_button, ui)
+                .was_clicked()
+                && !self.running
+            {
+                // cycle through a few colors that show up on the background
+                let next = TEXT_COLORS
+                    .iter()
+                    .position(|&c| c == text_color)
+                    .map_or(0, |i| (i + 1) % TEXT_COLORS.len());
+                self.record("Change text");
+                if let Some(text) = self.parts[index].as_text_mut() {
+                    text.set_color(TEXT_COLORS[next]);
+                }
+            }
+            if let Some(attach) = widget::Toggle::new(attached)
+                .parent(ids.part_canvas)
+                .down_from(ids.part_text_color_button, BUTTON_MARGIN)
+                .wh([20.0, 20.0])
+                .set(ids.part_text_attach_toggle, ui)
+                .last()
+            {
+                if !self.running {
+                    self.attach_text(index, attach);
+                }
+            }
+            widget::Text::new("Attached")
+                .font_size(12)
+                .right_from(ids.part_text_attach_toggle, BUTTON_MARGIN)
+                .wh([60.0, 20.0])
+                .set(ids.part_text_attach_text, ui);
+        }
+
+        if self.show_settings {
+            widget::Canvas::new()
+                .color(color::PURPLE)
+                .wh([200.0, 200.0])
+                .top_right_with_margins(80.0 + BUTTON_MARGIN, BUTTON_MARGIN)
+                .set(ids.settings_canvas, ui);
+            widget::Text::new("Settings")
+                .color(color::WHITE)
+                .font_size(12)
+                .parent(ids.settings_canvas)
+                .mid_top_with_margin(BUTTON_MARGIN)
+                .set(ids.settings_title_text, ui);
+            if let Some(depth) = widget::NumberDialer::new(
+                self.history.depth() as f64,
+                0.0,
+                history::MAX_DEPTH as f64,
+                0,
+            )
+            .label("Undo depth")
+            .label_font_size(12)
+            .parent(ids.settings_canvas)
+            .down_from(ids.settings_title_text, BUTTON_MARGIN)
+            .align_middle_x_of(ids.settings_canvas)
+            .wh([180.0, 20.0])
+            .set(ids.settings_history_depth_dialer, ui)
+            {
+                self.history.set_depth(depth as usize);
+            }
+            if let Some(zoom_to_fit) = widget::Toggle::new(self.zoom_to_fit)
+                .parent(ids.settings_canvas)
+                .down_from(ids.settings_history_depth_dialer, BUTTON_MARGIN)
+                .wh([20.0, 20.0])
+                .set(ids.settings_zoom_to_fit_toggle, ui)
+                .last()
+            {
+                self.zoom_to_fit = zoom_to_fit;
+            }
+            widget::Text::new("Zoom to fit camera focus")
+                .color(color::WHITE)
+                .font_size(12)
+                .right_from(ids.settings_zoom_to_fit_toggle, BUTTON_MARGIN)
+                .wh([150.0, 20.0])
+                .set(ids.settings_zoom_to_fit_text, ui);
+            if widget::Button::new()
+                .color(UTILITY_BUTTON_COLOR)
+                .label_font_size(12)
+                .label("Close")
+                .parent(ids.settings_canvas)
+                .mid_bottom_with_margin(BUTTON_MARGIN)
+                .wh([60.0, 20.0])
+                .set(ids.settings_close_button, ui)
+                .was_clicked()
+            {
+                self.show_settings = false;
+            }
+        }
+
+        if self.show_scale {
+            widget::Canvas::new()
+                .color(color::PURPLE)
+                .wh([200.0, 100.0])
+                // below the settings, in case both are open
+                .top_right_with_margins(80.0 + 200.0 + BUTTON_MARGIN * 2.0, BUTTON_MARGIN)
+                .set(ids.scale_canvas, ui);
+            widget::Text::new("Scale selection")
+                .color(color::WHITE)
+                .font_size(12)
+                .parent(ids.scale_canvas)
+                .mid_top_with_margin(BUTTON_MARGIN)
+                .set(ids.scale_title_text, ui);
+            if let Some(factor) = widget::NumberDialer::new(
+                self.scale_factor,
+                limits::MIN_SCALE_FACTOR,
+                limits::MAX_SCALE_FACTOR,
+                2,
+            )
+            .label("Factor")
+            .label_font_size(12)
+            .parent(ids.scale_canvas)
+            .down_from(ids.scale_title_text, BUTTON_MARGIN)
+            .align_middle_x_of(ids.scale_canvas)
+            .wh([180.0, 20.0])
+            .set(ids.scale_factor_dialer, ui)
+            {
+                self.scale_factor = factor;
+            }
+            if widget::Button::new()
+                .color(UTILITY_BUTTON_COLOR)
+                .label_font_size(12)
+                .label("Scale")
+                .parent(ids.scale_canvas)
+                .bottom_left_with_margin(BUTTON_MARGIN)
+                .wh([60.0, 20.0])
+                .set(ids.scale_apply_button, ui)
+                .was_clicked()
+            {
+                self.scale_selected(self.scale_factor);
+            }
+            if widget::Button::new()
+                .color(UTILITY_BUTTON_COLOR)
+                .label_font_size(12)
+                .label("Close")
+                .parent(ids.scale_canvas)
+                .bottom_right_with_margin(BUTTON_MARGIN)
+                .wh([60.0, 20.0])
+                .set(ids.scale_close_button, ui)
+                .was_clicked()
+            {
+                self.show_scale = false;
+            }
+        }
+
+        if self.show_layers {
+            const ROW_HEIGHT: Scalar = 20.0 + BUTTON_MARGIN;
+            const NAME_WIDTH: Scalar = 100.0;
+            let count = collision::LAYER_COUNT as Scalar;
+            widget::Canvas::new()
+                .color(color::PURPLE)
+                .wh([
+                    NAME_WIDTH + count * ROW_HEIGHT + BUTTON_MARGIN * 2.0,
+                    (count + 3.0) * ROW_HEIGHT + BUTTON_MARGIN,
+                ])
+                // below the scale tool, in case both are open
+                .top_right_with_margins(80.0 + 300.0 + BUTTON_MARGIN * 3.0, BUTTON_MARGIN)
+                .set(ids.layers_canvas, ui);
+            widget::Text::new("Collision layers")
+                .color(color::WHITE)
+                .font_size(12)
+                .parent(ids.layers_canvas)
+                .mid_top_with_margin(BUTTON_MARGIN)
+                .set(ids.layers_title_text, ui);
+            // the columns are numbered in the same order as the rows
+            for column in 0..collision::LAYER_COUNT {
+                widget::Text::new(&(column + 1).to_string())
+                    .color(color::WHITE)
+                    .font_size(12)
+                    .parent(ids.layers_canvas)
+                    .top_left_with_margins_on(
+                        ids.layers_canvas,
+                        ROW_HEIGHT + BUTTON_MARGIN,
+                        NAME_WIDTH + BUTTON_MARGIN * 2.0 + column as Scalar * ROW_HEIGHT,
+                    )
+                    .wh([20.0, 20.0])
+                    .set(ids.layers_column_texts[column], ui);
+            }
+            for row in 0..collision::LAYER_COUNT {
+                let top = (row as Scalar + 2.0) * ROW_HEIGHT + BUTTON_MARGIN;
+                for event in widget::TextBox::new(self.layers.name(row))
+                    .font_size(12)
+                    .parent(ids.layers_canvas)
+                    .top_left_with_margins_on(ids.layers_canvas, top, BUTTON_MARGIN)
+                    .wh([NAME_WIDTH, 20.0])
+                    .set(ids.layers_name_boxes[row], ui)
+                {
+                    if let widget::text_box::Event::Update(name) = event {
+                        self.history.record_merged(
+                            "Rename layer",
+                            &[row],
+                            &self.parts,
+                            &self.layers,
+                        );
+                        self.layers.set_name(row, name);
+                    }
+                }
+                for column in 0..collision::LAYER_COUNT {
+                    let id = ids.layers_matrix_toggles[row * collision::LAYER_COUNT + column];
+                    if let Some(collides) = widget::Toggle::new(self.layers.collides(row, column))
+                        .parent(ids.layers_canvas)
+                        .top_left_with_margins_on(
+                            ids.layers_canvas,
+                            top,
+                            NAME_WIDTH + BUTTON_MARGIN * 2.0 + column as Scalar * ROW_HEIGHT,
+                        )
+                        .wh([20.0, 20.0])
+                        .set(id, ui)
+                        .last()
+                    {
+                        self.record("Change layer collisions");
+                        self.layers.set_collides(row, column, collides);
+                    }
+                }
+            }
+            if widget::Button::new()
+                .color(UTILITY_BUTTON_COLOR)
+                .label_font_size(12)
+                .label("Close")
+                .parent(ids.layers_canvas)
+                .mid_bottom_with_margin(BUTTON_MARGIN)
+                .wh([60.0, 20.0])
+                .set(ids.layers_close_button, ui)
+                .was_clicked()
+            {
+                self.show_layers = false;
+            }
+        }
+
+        let mut close_playback = false;
+        if let Some(playback) = &mut self.playback {
+            widget::Canvas::new()
+                .color(color::PURPLE)
+                .wh([640.0, 30.0])
+                .mid_bottom_with_margin(BUTTON_MARGIN)
+                .set(ids.replay_canvas, ui);
+            if widget::Button::new()
+                .color(UTILITY_BUTTON_COLOR)
+                .label_font_size(12)
+                .label(if playback.paused() { "Play" } else { "Pause" })
+                .parent(ids.replay_canvas)
+                .mid_left_with_margin(BUTTON_MARGIN)
+                .wh([60.0, 20.0])
+                .set(ids.replay_pause_button, ui)
+                .was_clicked()
+            {
+                playback.toggle_pause();
+            }
+            let last_frame = playback.last_frame();
+            if let Some(frame) =
+                widget::Slider::new(playback.frame() as f64, 0.0, last_frame.max(1.0))
+                    .label_font_size(12)
+                    .label(&format!("Frame {}/{}", playback.frame(), last_frame))
+                    .parent(ids.replay_canvas)
+                    .right_from(ids.replay_pause_button, BUTTON_MARGIN)
+                    .wh([200.0, 20.0])
+                    .set(ids.replay_seek_slider, ui)
+            {
+                playback.seek(frame);
+            }
+            if let Some(speed) = widget::Slider::new(playback.speed(), 0.25, 4.0)
+                .label_font_size(12)
+                .label(&format!("Speed {:.2}x", playback.speed()))
+                .parent(ids.replay_canvas)
+                .right_from(ids.replay_seek_slider, BUTTON_MARGIN)
+                .wh([120.0, 20.0])
+                .set(ids.replay_speed_slider, ui)
+            {
+                playback.set_speed(speed);
+            }
+            let keys = playback
+                .keys()
+                .iter()
+                .map(|key| format!("{:?}", key))
+                .collect::<Vec<_>>()
+                .join(" ");
+            widget::Text::new(&keys)
+                .color(color::WHITE)
+                .font_size(12)
+                .parent(ids.replay_canvas)
+                .right_from(ids.replay_speed_slider, BUTTON_MARGIN)
+                .wh([120.0, 20.0])
+                .set(ids.replay_keys_text, ui);
+            close_playback = widget::Button::new()
+                .color(color::LIGHT_RED)
+                .label_font_size(12)
+                .label("Close")
+                .parent(ids.replay_canvas)
+                .mid_right_with_margin(BUTTON_MARGIN)
+                .wh([60.0, 20.0])
+                .set(ids.replay_close_button, ui)
+                .was_clicked();
+        }
+        if close_playback {
+            self.playback = None;
+            self.status.clear();
+        }
     }
 
-    fn draw(&self, ctx: Context, gfx: &mut GlGraphics, _glyphs: &mut GlyphCache<'static>) {
+    fn draw(&self, ctx: Context, gfx: &mut GlGraphics, glyphs: &mut GlyphCache<'static>) {
+        if let Some(playback) = &self.playback {
+            self.visualizer
+                .draw_parts(&self.camera, playback.parts(), true, ctx, gfx, glyphs);
+            return;
+        }
+
+        if let Some(challenge) = &self.challenge {
+            self.visualizer
+                .draw_challenge(&self.camera, challenge.challenge(), ctx, gfx);
+        }
+
         self.visualizer
-            .draw_parts(&self.camera, &self.parts, self.running, ctx, gfx);
+            .draw_parts(&self.camera, &self.parts, self.running, ctx, gfx, glyphs);
 
         // for (_, _, _, manifold) in self.world.collider_world().contact_pairs(true) {
         //     for c in manifold.contacts() {
@@ -563,6 +2759,23 @@ impl Screen for GameScreen {
         //     }
         // }
 
+        if !self.running {
+            if let Some((min, max)) = self.selection_bounds() {
+                self.visualizer
+                    .draw_handles(&Handles::new(&self.camera, min, max), ctx, gfx);
+            }
+        }
+
+        if let Some(start) = self.selection_start {
+            self.visualizer.draw_selection_box(
+                &self.camera,
+                start,
+                self.mouse_position_world,
+                ctx,
+                gfx,
+            );
+        }
+
         self.visualizer.maybe_draw_action(
             &self.action,
             &self.camera,
@@ -574,15 +2787,58 @@ impl Screen for GameScreen {
     }
 
     fn key(&mut self, key: Key, pressed: bool) {
+        if pressed {
+            self.held_keys.insert(key);
+        } else {
+            self.held_keys.remove(&key);
+        }
+
+        if self.typing {
+            return;
+        }
+        if pressed {
+            if let Some((index, slot)) = self.binding.take() {
+                self.bind_key(index, slot, key);
+                return;
+            }
+        }
+        // keys driving the robot shouldn't also move the camera
+        if self.running && self.is_bound(key) {
+            return;
+        }
+
+        let ctrl = self.ctrl_held();
+        let shift = self.shift_held();
+
         match key {
-            Key::A | Key::Left if pressed => self.camera.trans(&Vector2::new(-10.0, 0.0)),
-            Key::D | Key::Right if pressed => self.camera.trans(&Vector2::new(10.0, 0.0)),
-            Key::W | Key::Up if pressed => self.camera.trans(&Vector2::new(0.0, -10.0)),
-            Key::S | Key::Dollar if pressed => self.camera.trans(&Vector2::new(0.0, 10.0)),
-            Key::Plus | Key::NumPadPlus if pressed => self.zoom_in(),
-            Key::Minus | Key::NumPadMinus if pressed => self.zoom_out(),
+            Key::Z if pressed && ctrl && shift => self.redo(),
+            Key::Z if pressed && ctrl => self.undo(),
+            Key::Y if pressed && ctrl => self.redo(),
+            Key::Delete if pressed => self.delete_selected(),
+            Key::Return if pressed && self.action.kind() == ActionKind::CreatingPolygon => {
+                self.finish_polygon()
+            }
+            Key::Escape
+                if pressed
+                    && (self.action.kind() == ActionKind::CreatingPolygon
+                        || self.action.kind() == ActionKind::PlacingParts) =>
+            {
+                self.cancel_action();
+                self.status.clear();
+            }
+            Key::Home if pressed => self.move_to_front(),
+            Key::End if pressed => self.move_to_back(),
+            Key::PageUp if pressed => self.move_forward(),
+            Key::PageDown if pressed => self.move_backward(),
+            Key::X if pressed && ctrl => self.cut_selected(),
+            Key::C if pressed && ctrl => self.copy_selected(),
+            Key::V if pressed && ctrl => self.paste(Some(self.mouse_position_world)),
+            Key::Plus | Key::NumPadPlus if pressed => self.zoom_in(self.screen_center()),
+            Key::Minus | Key::NumPadMinus if pressed => self.zoom_out(self.screen_center()),
             Key::Space if pressed => {
-                if self.running {
+                if let Some(playback) = &mut self.playback {
+                    playback.toggle_pause();
+                } else if self.running {
                     self.stop();
                 } else {
                     self.start();
@@ -593,9 +2849,11 @@ impl Screen for GameScreen {
     }
 
     fn mouse(&mut self, button: MouseButton, pressed: bool) {
-        self.middle_mouse_down = button == MouseButton::Middle && pressed;
+        if button == MouseButton::Middle {
+            self.middle_mouse_down = pressed;
+        }
         match button {
-            MouseButton::Left if pressed => {
+            MouseButton::Left if pressed && !self.mouse_over_gui => {
                 if let Some(body) = util::get_body_at_mouse(&self.world, &self.mouse_position_world)
                 {
                     self.grabbed_object = Some(body);
@@ -641,7 +2899,7 @@ impl Screen for GameScreen {
                                     .position_p(self.action.first_click_world())
                                     // .selected(true)
                                     .build();
-                                self.parts.push(Box::new(circle));
+                                self.add_part(Box::new(circle));
                                 self.action.reset();
                             }
                             _ => {}
@@ -685,11 +2943,11 @@ impl Screen for GameScreen {
                                         -limits::MAX_RECTANGLE_SIZE,
                                     )
                                 };
-                                let rectangle = ShapeBuilder::rectangle(width, height)
+                                let rectangle = ShapeBuilder::rectangle(width.abs(), height.abs())
                                     .position_p(self.action.first_click_world())
                                     // .selected(true)
                                     .build();
-                                self.parts.push(Box::new(rectangle));
+                                self.add_part(Box::new(rectangle));
                                 self.action.reset();
                             }
                             _ => {}
@@ -709,86 +2967,73 @@ impl Screen for GameScreen {
                                     .set_second_click_world(self.mouse_position_world);
                             }
                             2 => {
-                                let triangle = ShapeBuilder::triangle(
-                                    nalgebra::Vector2::new(
-                                        self.action.first_click_world().x,
-                                        self.action.first_click_world().y,
-                                    ),
-                                    nalgebra::Vector2::new(
-                                        self.action.second_click_world().x,
-                                        self.action.second_click_world().y,
-                                    ),
-                                    nalgebra::Vector2::new(
-                                        self.mouse_position_world.x,
-                                        self.mouse_position_world.y,
-                                    ),
-                                )
-                                // .selected(true)
-                                .build();
-                                self.parts.push(Box::new(triangle));
+                                let p1 = self.action.first_click_world().coords;
+                                let p2 = self.action.second_click_world().coords;
+                                let p3 = self.mouse_position_world.coords;
+                                // keep the points around the center, so it rotates around it
+                                let center = (p1 + p2 + p3) / 3.0;
+                                let triangle =
+                                    ShapeBuilder::triangle(p1 - center, p2 - center, p3 - center)
+                                        .position(center)
+                                        // .selected(true)
+                                        .build();
+                                self.add_part(Box::new(triangle));
                                 self.action.reset();
                             }
                             _ => {}
                         }
                     }
-                    // ActionKind::CreatingSlidingJoint => match self.action.step() {
-                    //     0 => {
-                    //         self.action.advance_step();
-                    //         self.action.set_first_click(self.mouse_position);
-                    //         self.action.set_first_click_world(self.mouse_position_world);
-                    //     }
-                    //     1 => {
-                    //         if let (Some(part1_index), Some(part2_index)) = (
-                    //             self.get_part_at(self.action.first_click()),
-                    //             self.get_part_at(self.mouse_position),
-                    //         ) {
-                    //             match &self.parts[part1_index] {
-                    //                 Part::Shape(s) => log::trace!("Part 1 kind: {:?}", s.kind()),
-                    //                 Part::Joint(j) => log::trace!("Part 1 kind {:?}", j.kind()),
-                    //             }
-                    //             match &self.parts[part2_index] {
-                    //                 Part::Shape(s) => log::trace!("Part 2 kind: {:?}", s.kind()),
-                    //                 Part::Joint(j) => log::trace!("Part 2 kind {:?}", j.kind()),
-                    //             }
-                    //             if let (Part::Shape(shape1), Part::Shape(shape2)) =
-                    //                 (&self.parts[part1_index], &self.parts[part2_index])
-                    //             {
-                    //                 log::trace!(
-                    //                     "Part 1 kind: {:?} part 2 kind: {:?}",
-                    //                     shape1.kind(),
-                    //                     shape2.kind()
-                    //                 );
-                    //                 let pos = shape2.iso().translation.vector;
-                    //                 let anchor1 =
-                    //                     Point2::new(self.action.first_click_world().x, pos.x);
-                    //                 let anchor2 =
-                    //                     Point2::new(self.action.first_click_world().y, pos.y);
-                    //                 let axis = Vector2::new(
-                    //                     pos.x - self.action.first_click_world().x,
-                    //                     pos.y - self.action.first_click_world().y,
-                    //                 );
-                    //                 let joint = JointBuilder::prismatic(shape1, shape2)
-                    //                     .anchor1(anchor1)
-                    //                     .anchor2(anchor2)
-                    //                     .axis(axis)
-                    //                     .build();
-                    //                 self.parts.push(Part::Joint(joint));
-                    //                 self.action.reset();
-                    //             }
-                    //         }
-                    //         self.action.reset();
-                    //     }
-                    //     _ => {}
-                    // },
+                    ActionKind::PlacingParts => {
+                        if self.place_parts(self.mouse_position_world) {
+                            self.action.reset();
+                            self.status.clear();
+                        }
+                    }
+                    ActionKind::CreatingFixedJoint
+                    | ActionKind::CreatingRotatingJoint
+                    | ActionKind::CreatingSlidingJoint => match self.action.step() {
+                        0 => self.start_joint(),
+                        1 => {
+                            if self.action.kind() == ActionKind::CreatingSlidingJoint {
+                                self.add_sliding_joint(self.mouse_position_world);
+                            } else {
+                                self.add_pinned_joint(self.mouse_position_world);
+                            }
+                            self.action.reset();
+                        }
+                        _ => {}
+                    },
+                    ActionKind::CreatingThruster => {
+                        self.add_thruster(self.mouse_position_world);
+                        self.action.reset();
+                    }
+                    ActionKind::CreatingCannon => {
+                        self.add_cannon(self.mouse_position_world);
+                        self.action.reset();
+                    }
+                    ActionKind::CreatingText => {
+                        self.add_text(self.mouse_position_world);
+                        self.action.reset();
+                    }
+                    ActionKind::CreatingPolygon => {
+                        self.add_polygon_point(self.mouse_position_world);
+                    }
+                    ActionKind::None if !self.running && self.playback.is_none() => {
+                        self.start_drag();
+                    }
                     _ => {}
                 }
             }
             MouseButton::Left if !pressed => {
+                // whatever was being dragged is done, the next drag is its own edit
+                self.history.end_merge();
                 if let Some(joint) = self.grabbed_object_constraint {
                     let _ = self.world.remove_constraint(joint);
                 }
                 self.grabbed_object = None;
                 self.grabbed_object_constraint = None;
+                self.finish_box_select();
+                self.finish_drag();
             }
             _ => {}
         }
@@ -810,22 +3055,21 @@ impl Screen for GameScreen {
                 .unwrap();
             joint.set_anchor_1(self.mouse_position_world);
         }
+
+        self.drag();
     }
 
     fn mouse_relative(&mut self, x: f64, y: f64) {
         if self.middle_mouse_down && self.grabbed_object.is_none() {
-            const MAX_MOVEMENT: f64 = 0.5;
-            let x = util::clamp(x, -MAX_MOVEMENT, MAX_MOVEMENT);
-            let y = util::clamp(y, -MAX_MOVEMENT, MAX_MOVEMENT);
-            self.camera.trans(&Vector2::new(x, y));
+            self.camera.drag(Vector2::new(x, y));
         }
     }
 
     fn mouse_scroll(&mut self, _x: f64, y: f64) {
         if y < 0.0 {
-            self.zoom_out();
+            self.zoom_out(self.mouse_position);
         } else {
-            self.zoom_in();
+            self.zoom_in(self.mouse_position);
         }
     }
 
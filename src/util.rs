@@ -1,11 +1,12 @@
+use crate::collision;
+use crate::part::Part;
 use nalgebra::Point2;
-use ncollide2d::world::CollisionGroups;
 use nphysics2d::object::{BodyPartHandle, ColliderAnchor};
 use nphysics2d::world::World;
 
 // this will return a body (if any) at the current point
 pub fn get_body_at_mouse(world: &World<f64>, point: &Point2<f64>) -> Option<BodyPartHandle> {
-    let groups = CollisionGroups::default();
+    let groups = collision::query_groups();
     for b in world
         .collider_world()
         .interferences_with_point(point, &groups)
@@ -33,3 +34,81 @@ where
         val
     }
 }
+
+// the indices of the parts from the bottom up, parts with the same z order keep their order
+pub fn draw_order(parts: &[Box<dyn Part>]) -> Vec<usize> {
+    let mut order = (0..parts.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&index| parts[index].z_order());
+    order
+}
+
+// the smallest box containing all of the given parts, as (min, max)
+pub fn bounds<'a, I>(parts: I) -> Option<(Point2<f64>, Point2<f64>)>
+where
+    I: IntoIterator<Item = &'a dyn Part>,
+{
+    merge_bounds(parts.into_iter().map(|part| part.bounds()))
+}
+
+// the smallest box containing all of the given boxes
+pub fn merge_bounds<I>(bounds: I) -> Option<(Point2<f64>, Point2<f64>)>
+where
+    I: IntoIterator<Item = (Point2<f64>, Point2<f64>)>,
+{
+    bounds.into_iter().fold(None, |acc, (min, max)| {
+        Some(match acc {
+            Some((acc_min, acc_max)) => {
+                (nalgebra::inf(&acc_min, &min), nalgebra::sup(&acc_max, &max))
+            }
+            None => (min, max),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::part::ShapeBuilder;
+    use nalgebra::Vector2;
+
+    #[test]
+    fn clamp_keeps_values_in_range() {
+        assert_eq!(clamp(5, 0, 10), 5);
+        assert_eq!(clamp(-5, 0, 10), 0);
+        assert_eq!(clamp(15, 0, 10), 10);
+    }
+
+    #[test]
+    fn bounds_contain_every_part() {
+        let parts: Vec<Box<dyn Part>> = vec![
+            Box::new(
+                ShapeBuilder::circle(1.0)
+                    .position(Vector2::new(-2.0, 0.0))
+                    .build(),
+            ),
+            Box::new(
+                ShapeBuilder::rectangle(1.0, 2.0)
+                    .position(Vector2::new(3.0, 1.0))
+                    .build(),
+            ),
+        ];
+        let (min, max) = bounds(parts.iter().map(|part| part.as_ref())).unwrap();
+        assert_eq!(min, Point2::new(-3.0, -1.0));
+        assert_eq!(max, Point2::new(4.0, 3.0));
+    }
+
+    #[test]
+    fn bounds_of_nothing() {
+        assert_eq!(bounds(Vec::<&dyn Part>::new()), None);
+    }
+
+    #[test]
+    fn draw_order_is_stable() {
+        let mut parts: Vec<Box<dyn Part>> = (0..4)
+            .map(|_| Box::new(ShapeBuilder::circle(1.0).build()) as Box<dyn Part>)
+            .collect();
+        parts[0].set_z_order(2);
+        parts[2].set_z_order(-1);
+        assert_eq!(draw_order(&parts), vec![2, 1, 3, 0]);
+    }
+}
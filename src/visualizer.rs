@@ -1,18 +1,92 @@
 use crate::action::{Action, ActionKind};
 use crate::camera::Camera;
+use crate::challenge::{Challenge, Goal, Region};
 use crate::limits;
-use crate::part::{Joint, JointKind, Part, Shape, ShapeKind};
+use crate::part::{Cannon, Joint, JointKind, Part, Shape, ShapeKind, Text, Thruster};
 use crate::util;
 use graphics::{Colored, Context, Transformed};
-use nalgebra::{Isometry2, Point2, Vector2};
+use nalgebra::{Point2, Vector2};
 use nphysics2d::world::World;
-use opengl_graphics::GlGraphics;
+use opengl_graphics::{GlGraphics, GlyphCache};
 
-pub struct Visualizer {}
+// selected parts are outlined with this
+const SELECTION_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+
+// shapes are drawn with this when colors are hidden
+const FLAT_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+// how big the selection handles are on screen
+pub const HANDLE_SIZE: f64 = 8.0;
+// how big joints are drawn on screen
+const JOINT_SIZE: f64 = 10.0;
+// how long the arrow showing which way a thruster pushes, or a cannon fires, is on screen
+const THRUSTER_ARROW_LENGTH: f64 = 20.0;
+// how far above the selection the rotation handle sits on screen
+const ROTATION_HANDLE_OFFSET: f64 = 20.0;
+
+// where the handles around a selection are, in screen space
+pub struct Handles {
+    // used for resizing
+    pub corners: [Vector2<f64>; 4],
+    pub rotation: Vector2<f64>,
+}
+
+impl Handles {
+    pub fn new(camera: &Camera, min: Point2<f64>, max: Point2<f64>) -> Self {
+        let min = camera.to_global(min.coords);
+        let max = camera.to_global(max.coords);
+        Handles {
+            corners: [
+                min,
+                Vector2::new(max.x, min.y),
+                max,
+                Vector2::new(min.x, max.y),
+            ],
+            rotation: Vector2::new((min.x + max.x) / 2.0, min.y - ROTATION_HANDLE_OFFSET),
+        }
+    }
+}
+
+// how parts are drawn, these only change what we see and never the robot
+pub struct Visualizer {
+    show_joints: bool,
+    // otherwise every shape is drawn the same flat color
+    show_colors: bool,
+    // shapes are drawn without being filled in, so what's behind them can be seen
+    outlines_only: bool,
+}
 
 impl Visualizer {
     pub fn new() -> Self {
-        Visualizer {}
+        Visualizer {
+            show_joints: true,
+            show_colors: true,
+            outlines_only: false,
+        }
+    }
+
+    pub fn show_joints(&self) -> bool {
+        self.show_joints
+    }
+
+    pub fn set_show_joints(&mut self, show_joints: bool) {
+        self.show_joints = show_joints;
+    }
+
+    pub fn show_colors(&self) -> bool {
+        self.show_colors
+    }
+
+    pub fn set_show_colors(&mut self, show_colors: bool) {
+        self.show_colors = show_colors;
+    }
+
+    pub fn outlines_only(&self) -> bool {
+        self.outlines_only
+    }
+
+    pub fn set_outlines_only(&mut self, outlines_only: bool) {
+        self.outlines_only = outlines_only;
     }
 
     pub fn maybe_draw_action(
@@ -114,7 +188,30 @@ impl Visualizer {
                     }
                 }
             }
-            ActionKind::CreatingSlidingJoint if action.step() == 1 => {
+            ActionKind::CreatingPolygon => {
+                // the edges placed so far, and one more following the mouse
+                let mut corners = action
+                    .points()
+                    .iter()
+                    .map(|point| camera.to_global(point.coords))
+                    .collect::<Vec<_>>();
+                if !corners.is_empty() {
+                    corners.push(mouse_position);
+                }
+                for pair in corners.windows(2) {
+                    graphics::Line::new(graphics::color::BLACK, 1.0).draw(
+                        [pair[0].x, pair[0].y, pair[1].x, pair[1].y],
+                        &graphics::DrawState::default(),
+                        ctx.transform,
+                        gfx,
+                    );
+                }
+            }
+            ActionKind::CreatingFixedJoint
+            | ActionKind::CreatingRotatingJoint
+            | ActionKind::CreatingSlidingJoint
+                if action.step() == 1 =>
+            {
                 graphics::Line::new(graphics::color::BLACK, 1.0).draw(
                     [
                         action.first_click().x,
@@ -144,7 +241,20 @@ impl Visualizer {
         } else {
             shape.iso()
         };
-        let color = shape.color();
+        let color = if self.show_colors {
+            shape.color()
+        } else {
+            FLAT_COLOR
+        };
+        let border_color = if shape.selected() && !running {
+            SELECTION_COLOR
+        } else if self.outlines_only {
+            color
+        } else {
+            color.shade(0.5)
+        };
+        // a see through fill leaves just the border
+        let color = if self.outlines_only { [0.0; 4] } else { color };
         let (position, rotation) = (iso.translation.vector, iso.rotation.angle());
         let position = camera.to_global(position);
         let xf = ctx
@@ -152,12 +262,13 @@ impl Visualizer {
             .rot_rad(rotation)
             .zoom(camera.zoom())
             .transform;
-        match shape.kind() {
+        let outlined = self.outlines_only || (shape.selected() && !running);
+        match *shape.kind() {
             ShapeKind::Circle { radius } => {
                 use graphics::ellipse::Border;
                 graphics::Ellipse::new(color)
                     .border(Border {
-                        color: color.shade(0.5),
+                        color: border_color,
                         radius: 0.1,
                     })
                     .resolution(16)
@@ -175,7 +286,7 @@ impl Visualizer {
                 use graphics::rectangle::Border;
                 graphics::Rectangle::new(color)
                     .border(Border {
-                        color: color.shade(0.5),
+                        color: border_color,
                         radius: 0.1,
                     })
                     .draw(
@@ -191,8 +302,32 @@ impl Visualizer {
                     );
             }
             ShapeKind::Triangle { p1, p2, p3 } => {
-                graphics::Polygon::new(color).draw(
-                    &[[p1.x, p1.y], [p2.x, p2.y], [p3.x, p3.y]],
+                self.draw_polygon(&[p1, p2, p3], color, border_color, outlined, xf, gfx);
+            }
+            ShapeKind::Polygon { ref points } => {
+                self.draw_polygon(points, color, border_color, outlined, xf, gfx);
+            }
+        }
+    }
+
+    fn draw_polygon(
+        &self,
+        points: &[Vector2<f64>],
+        color: [f32; 4],
+        border_color: [f32; 4],
+        // outlines are drawn around all shapes, or just the selected ones
+        outlined: bool,
+        xf: graphics::math::Matrix2d,
+        gfx: &mut GlGraphics,
+    ) {
+        let corners = points.iter().map(|p| [p.x, p.y]).collect::<Vec<_>>();
+        graphics::Polygon::new(color).draw(&corners, &graphics::DrawState::default(), xf, gfx);
+        if outlined {
+            let line = graphics::Line::new(border_color, 0.05);
+            for (i, a) in points.iter().enumerate() {
+                let b = points[(i + 1) % points.len()];
+                line.draw(
+                    [a.x, a.y, b.x, b.y],
                     &graphics::DrawState::default(),
                     xf,
                     gfx,
@@ -209,22 +344,302 @@ impl Visualizer {
         ctx: Context,
         gfx: &mut GlGraphics,
     ) {
-        let anchor1 = camera.to_global(Vector2::new(joint.anchor1().x, joint.anchor1().y));
-        let anchor2 = camera.to_global(Vector2::new(joint.anchor2().x, joint.anchor2().y));
+        let anchor1 = camera.to_global(joint.world_anchor1().coords);
+        let anchor2 = camera.to_global(joint.world_anchor2().coords);
         let xf = ctx.transform;
+        let color = if joint.selected() && !running {
+            SELECTION_COLOR
+        } else {
+            [1.0, 1.0, 1.0, 1.0]
+        };
         match joint.kind() {
             JointKind::Prismatic => {
-                graphics::Line::new([1.0, 1.0, 1.0, 1.0], 1.0).draw(
+                // pistons are drawn thicker
+                let width = if joint.motor().enabled { 2.0 } else { 1.0 };
+                graphics::Line::new(color, width).draw(
                     [anchor1.x, anchor1.y, anchor2.x, anchor2.y],
                     &graphics::DrawState::default(),
                     xf,
                     gfx,
                 );
             }
-            _ => {}
+            JointKind::Revolute => {
+                // a ring around the pin, filled in when there is a motor
+                let size = JOINT_SIZE / 2.0;
+                let square = [-size, -size, size * 2.0, size * 2.0];
+                let xf = ctx.trans(anchor1.x, anchor1.y).transform;
+                if joint.motor().enabled {
+                    graphics::Ellipse::new(color).draw(
+                        square,
+                        &graphics::DrawState::default(),
+                        xf,
+                        gfx,
+                    );
+                } else {
+                    graphics::Ellipse::new_border(color, 1.0).draw(
+                        square,
+                        &graphics::DrawState::default(),
+                        xf,
+                        gfx,
+                    );
+                }
+            }
+            JointKind::Fixed => {
+                let size = JOINT_SIZE / 2.0;
+                let xf = ctx.trans(anchor1.x, anchor1.y).transform;
+                graphics::Rectangle::new_border(color, 1.0).draw(
+                    [-size, -size, size * 2.0, size * 2.0],
+                    &graphics::DrawState::default(),
+                    xf,
+                    gfx,
+                );
+                graphics::Line::new(color, 1.0).draw(
+                    [-size, -size, size, size],
+                    &graphics::DrawState::default(),
+                    xf,
+                    gfx,
+                );
+                graphics::Line::new(color, 1.0).draw(
+                    [-size, size, size, -size],
+                    &graphics::DrawState::default(),
+                    xf,
+                    gfx,
+                );
+            }
         }
     }
 
+    fn draw_thruster(
+        &self,
+        camera: &Camera,
+        thruster: &Thruster,
+        running: bool,
+        ctx: Context,
+        gfx: &mut GlGraphics,
+    ) {
+        let iso = if running {
+            thruster.world_iso()
+        } else {
+            thruster.iso()
+        };
+        let position = camera.to_global(iso.translation.vector);
+        let color = if thruster.selected() && !running {
+            SELECTION_COLOR
+        } else {
+            [1.0, 1.0, 1.0, 1.0]
+        };
+        // the nozzle, with an arrow pointing the way we push
+        let size = JOINT_SIZE / 2.0;
+        let xf = ctx
+            .trans(position.x, position.y)
+            .rot_rad(iso.rotation.angle())
+            .transform;
+        graphics::Rectangle::new_border(color, 1.0).draw(
+            [-size, -size, size * 2.0, size * 2.0],
+            &graphics::DrawState::default(),
+            xf,
+            gfx,
+        );
+        graphics::Line::new(color, 1.0).draw_arrow(
+            [0.0, 0.0, THRUSTER_ARROW_LENGTH, 0.0],
+            size,
+            &graphics::DrawState::default(),
+            xf,
+            gfx,
+        );
+        if thruster.firing() {
+            graphics::Line::new([1.0, 0.5, 0.0, 1.0], 3.0).draw(
+                [-size, 0.0, -THRUSTER_ARROW_LENGTH, 0.0],
+                &graphics::DrawState::default(),
+                xf,
+                gfx,
+            );
+        }
+    }
+
+    fn draw_cannon(
+        &self,
+        camera: &Camera,
+        cannon: &Cannon,
+        running: bool,
+        ctx: Context,
+        gfx: &mut GlGraphics,
+    ) {
+        let iso = if running {
+            cannon.world_iso()
+        } else {
+            cannon.iso()
+        };
+        let position = camera.to_global(iso.translation.vector);
+        let color = if cannon.selected() && !running {
+            SELECTION_COLOR
+        } else {
+            [1.0, 1.0, 1.0, 1.0]
+        };
+        // a round base with the barrel pointing the way we fire
+        let size = JOINT_SIZE / 2.0;
+        let xf = ctx
+            .trans(position.x, position.y)
+            .rot_rad(iso.rotation.angle())
+            .transform;
+        graphics::Ellipse::new_border(color, 1.0).draw(
+            [-size, -size, size * 2.0, size * 2.0],
+            &graphics::DrawState::default(),
+            xf,
+            gfx,
+        );
+        graphics::Line::new(color, 2.0).draw(
+            [0.0, 0.0, THRUSTER_ARROW_LENGTH, 0.0],
+            &graphics::DrawState::default(),
+            xf,
+            gfx,
+        );
+        let radius = cannon.projectile_radius();
+        for projectile in cannon.projectiles() {
+            let position = camera.to_global(projectile.position().coords);
+            graphics::Ellipse::new([0.2, 0.2, 0.2, 1.0]).draw(
+                [-radius, -radius, radius * 2.0, radius * 2.0],
+                &graphics::DrawState::default(),
+                ctx.trans(position.x, position.y)
+                    .zoom(camera.zoom())
+                    .transform,
+                gfx,
+            );
+        }
+    }
+
+    fn draw_text(
+        &self,
+        camera: &Camera,
+        text: &Text,
+        running: bool,
+        ctx: Context,
+        gfx: &mut GlGraphics,
+        glyphs: &mut GlyphCache<'static>,
+    ) {
+        let iso = if running {
+            text.world_iso()
+        } else {
+            text.iso()
+        };
+        let position = camera.to_global(iso.translation.vector);
+        let color = if text.selected() && !running {
+            SELECTION_COLOR
+        } else {
+            text.color()
+        };
+        // glyphs are rendered at their size on screen, so they stay sharp when zoomed
+        let font_size = (text.size() * camera.zoom()).round().max(1.0) as u32;
+        let xf = ctx
+            .trans(position.x, position.y)
+            .rot_rad(iso.rotation.angle())
+            .transform;
+        if let Err(e) = graphics::Text::new_color(color, font_size).draw(
+            text.content(),
+            glyphs,
+            &graphics::DrawState::default(),
+            xf,
+            gfx,
+        ) {
+            log::error!("Couldn't draw text: {:?}", e);
+        }
+    }
+
+    fn draw_region(
+        &self,
+        camera: &Camera,
+        region: Region,
+        color: [f32; 4],
+        ctx: Context,
+        gfx: &mut GlGraphics,
+    ) {
+        let min = camera.to_global(region.min().coords);
+        let max = camera.to_global(region.max().coords);
+        graphics::Rectangle::new(color).draw(
+            [min.x, min.y, max.x - min.x, max.y - min.y],
+            &graphics::DrawState::default(),
+            ctx.transform,
+            gfx,
+        );
+    }
+
+    // draws the build area and the goal regions of a challenge, behind everything else
+    pub fn draw_challenge(
+        &self,
+        camera: &Camera,
+        challenge: &Challenge,
+        ctx: Context,
+        gfx: &mut GlGraphics,
+    ) {
+        self.draw_region(
+            camera,
+            challenge.build_area(),
+            [1.0, 1.0, 1.0, 0.1],
+            ctx,
+            gfx,
+        );
+        for goal in challenge.goals() {
+            if let Goal::Reach { region } = goal {
+                self.draw_region(camera, *region, [0.0, 1.0, 0.0, 0.2], ctx, gfx);
+            }
+        }
+    }
+
+    pub fn draw_handles(&self, handles: &Handles, ctx: Context, gfx: &mut GlGraphics) {
+        let square = [
+            -HANDLE_SIZE / 2.0,
+            -HANDLE_SIZE / 2.0,
+            HANDLE_SIZE,
+            HANDLE_SIZE,
+        ];
+        for corner in &handles.corners {
+            graphics::Rectangle::new(SELECTION_COLOR).draw(
+                square,
+                &graphics::DrawState::default(),
+                ctx.trans(corner.x, corner.y).transform,
+                gfx,
+            );
+        }
+        let top = Vector2::new(handles.rotation.x, handles.corners[0].y);
+        graphics::Line::new(SELECTION_COLOR, 0.5).draw(
+            [top.x, top.y, handles.rotation.x, handles.rotation.y],
+            &graphics::DrawState::default(),
+            ctx.transform,
+            gfx,
+        );
+        graphics::Ellipse::new(SELECTION_COLOR).draw(
+            square,
+            &graphics::DrawState::default(),
+            ctx.trans(handles.rotation.x, handles.rotation.y).transform,
+            gfx,
+        );
+    }
+
+    // the rubber band box used to select parts
+    pub fn draw_selection_box(
+        &self,
+        camera: &Camera,
+        start: Point2<f64>,
+        end: Point2<f64>,
+        ctx: Context,
+        gfx: &mut GlGraphics,
+    ) {
+        let start = camera.to_global(start.coords);
+        let end = camera.to_global(end.coords);
+        graphics::Rectangle::new_border(SELECTION_COLOR, 0.5).draw(
+            [
+                start.x.min(end.x),
+                start.y.min(end.y),
+                (end.x - start.x).abs(),
+                (end.y - start.y).abs(),
+            ],
+            &graphics::DrawState::default(),
+            ctx.transform,
+            gfx,
+        );
+    }
+
+    // text needs the font, so the glyphs are passed along with everything else
     pub fn draw_parts(
         &self,
         camera: &Camera,
@@ -232,13 +647,26 @@ impl Visualizer {
         running: bool,
         ctx: Context,
         gfx: &mut GlGraphics,
+        glyphs: &mut GlyphCache<'static>,
     ) {
-        for part in parts {
+        for index in util::draw_order(parts) {
+            let part = &parts[index];
             if let Some(s) = part.as_shape() {
                 self.draw_shape(camera, s, running, ctx, gfx);
             }
             if let Some(j) = part.as_joint() {
-                self.draw_joint(camera, j, running, ctx, gfx);
+                if self.show_joints {
+                    self.draw_joint(camera, j, running, ctx, gfx);
+                }
+            }
+            if let Some(t) = part.as_thruster() {
+                self.draw_thruster(camera, t, running, ctx, gfx);
+            }
+            if let Some(c) = part.as_cannon() {
+                self.draw_cannon(camera, c, running, ctx, gfx);
+            }
+            if let Some(t) = part.as_text() {
+                self.draw_text(camera, t, running, ctx, gfx, glyphs);
             }
         }
     }